@@ -1,17 +1,68 @@
-use rand::{seq::SliceRandom, Rng};
+use std::{collections::VecDeque, fmt, str::FromStr};
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+
+// Smallest maze the generators build, anything smaller is grown to this
+pub const MIN_SIZE: usize = 3;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Algorithm {
+    #[default]
+    Prim,
+    Backtracker,
+}
+
+impl Algorithm {
+    pub const ALL: [Algorithm; 2] = [Algorithm::Prim, Algorithm::Backtracker];
+
+    pub fn generate(self, row: usize, col: usize, seed: u64) -> Vec<Vec<char>> {
+        match self {
+            Algorithm::Prim => Prim::with_seed(row, col, seed),
+            Algorithm::Backtracker => Backtracker::with_seed(row, col, seed),
+        }
+    }
+}
+
+impl fmt::Display for Algorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Algorithm::Prim => write!(f, "prim"),
+            Algorithm::Backtracker => write!(f, "backtracker"),
+        }
+    }
+}
+
+impl FromStr for Algorithm {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "prim" => Ok(Algorithm::Prim),
+            "backtracker" | "dfs" => Ok(Algorithm::Backtracker),
+            _ => Err(format!("unknown algorithm `{}` (expected: prim, backtracker)", s)),
+        }
+    }
+}
 
 #[derive(Debug, Default, Clone, Copy)]
 pub struct Prim;
 
 impl Prim {
+    #[allow(clippy::new_ret_no_self)]
     pub fn new(row: usize, col: usize) -> Vec<Vec<char>> {
+        Self::with_seed(row, col, rand::thread_rng().gen())
+    }
+
+    // The same seed always produces the same maze
+    pub fn with_seed(row: usize, col: usize, seed: u64) -> Vec<Vec<char>> {
+        let (row, col) = (row.max(MIN_SIZE), col.max(MIN_SIZE));
+        let mut rng = StdRng::seed_from_u64(seed);
         let mut maz = vec![vec!['#'; col]; row];
 
         // First, enforce walls on all edges
         Self::enforce_edge_walls(&mut maz);
 
         // Select a random point on the edge as the start node
-        let (sr, sc) = Self::random_edge_point(row, col);
+        let (sr, sc) = Self::random_edge_point(row, col, &mut rng);
         let s = Point::new(sr, sc, None);
         maz[s.r][s.c] = 'S';
 
@@ -24,7 +75,7 @@ impl Prim {
 
         // Maze generation loop
         while !frontier.is_empty() {
-            let random_index = rand::thread_rng().gen_range(0..frontier.len());
+            let random_index = rng.gen_range(0..frontier.len());
             let curr = frontier.remove(random_index);
 
             if let Some(opposite) = curr.opposite(row, col) {
@@ -34,37 +85,35 @@ impl Prim {
                 // Allow path creation if:
                 // 1. Neither point is on the edge, OR
                 // 2. The edge point could be a valid end point
-                if (!curr_is_edge && !opposite_is_edge) || 
-                   (curr_is_edge && Self::is_valid_end_location(&curr, sr, sc)) ||
-                   (opposite_is_edge && Self::is_valid_end_location(&opposite, sr, sc)) {
-                    
-                    if maz[curr.r][curr.c] == '#' && maz[opposite.r][opposite.c] == '#' {
-                        // Open paths between nodes
-                        maz[curr.r][curr.c] = '.';
-                        maz[opposite.r][opposite.c] = '.';
-
-                        // If we created a path to an edge, store it as a potential end point
-                        if curr_is_edge && Self::is_valid_end_location(&curr, sr, sc) {
-                            potential_ends.push((curr.r, curr.c));
-                        }
-                        if opposite_is_edge && Self::is_valid_end_location(&opposite, sr, sc) {
-                            potential_ends.push((opposite.r, opposite.c));
-                        }
-
-                        // Add neighbors of the opposite point to the frontier
-                        Self::add_neighbors(opposite.r, opposite.c, row, col, &mut frontier, &opposite);
+                if ((!curr_is_edge && !opposite_is_edge) || 
+                    (curr_is_edge && Self::is_valid_end_location(&curr, sr, sc)) ||
+                    (opposite_is_edge && Self::is_valid_end_location(&opposite, sr, sc))) &&
+                   maz[curr.r][curr.c] == '#' && maz[opposite.r][opposite.c] == '#' {
+                    // Open paths between nodes
+                    maz[curr.r][curr.c] = '.';
+                    maz[opposite.r][opposite.c] = '.';
+
+                    // If we created a path to an edge, store it as a potential end point
+                    if curr_is_edge && Self::is_valid_end_location(&curr, sr, sc) {
+                        potential_ends.push((curr.r, curr.c));
+                    }
+                    if opposite_is_edge && Self::is_valid_end_location(&opposite, sr, sc) {
+                        potential_ends.push((opposite.r, opposite.c));
                     }
+
+                    // Add neighbors of the opposite point to the frontier
+                    Self::add_neighbors(opposite.r, opposite.c, row, col, &mut frontier, &opposite);
                 }
             }
         }
 
         // Choose a random potential end point
-        if let Some(&mut (er, ec)) = potential_ends.choose_mut(&mut rand::thread_rng()) {
+        if let Some(&mut (er, ec)) = potential_ends.choose_mut(&mut rng) {
             maz[er][ec] = 'E';
         } else {
             // If no potential ends were found during generation,
             // find one using the flood fill method
-            let (er, ec) = Self::find_connected_end_point(&maz, sr, sc);
+            let (er, ec) = Self::find_connected_end_point(&maz, sr, sc, &mut rng);
             maz[er][ec] = 'E';
         }
 
@@ -83,7 +132,7 @@ impl Prim {
         point.r != start_r || point.c != start_c
     }
 
-    fn find_connected_end_point(maz: &Vec<Vec<char>>, start_r: usize, start_c: usize) -> (usize, usize) {
+    fn find_connected_end_point(maz: &[Vec<char>], start_r: usize, start_c: usize, rng: &mut StdRng) -> (usize, usize) {
         let row = maz.len();
         let col = maz[0].len();
         
@@ -114,14 +163,14 @@ impl Prim {
         }
         
         if !reachable_edges.is_empty() {
-            let idx = rand::thread_rng().gen_range(0..reachable_edges.len());
+            let idx = rng.gen_range(0..reachable_edges.len());
             reachable_edges[idx]
         } else {
             // If we still can't find an end point, force create one
             // by finding the closest reachable point to an edge
-            for r in 0..row {
-                for c in 0..col {
-                    if visited[r][c] && 
+            for (r, line) in visited.iter().enumerate() {
+                for (c, &seen) in line.iter().enumerate() {
+                    if seen && 
                        (r == 0 || r == row-1 || c == 0 || c == col-1) && 
                        (r != start_r || c != start_c) {
                         return (r, c);
@@ -153,8 +202,7 @@ impl Prim {
         }
     }
 
-    fn random_edge_point(row: usize, col: usize) -> (usize, usize) {
-        let mut rng = rand::thread_rng();
+    fn random_edge_point(row: usize, col: usize, rng: &mut StdRng) -> (usize, usize) {
         match rng.gen_range(0..4) {
             0 => (0, rng.gen_range(0..col)),             // Top edge
             1 => (row - 1, rng.gen_range(0..col)),       // Bottom edge
@@ -163,20 +211,338 @@ impl Prim {
         }
     }
 
-    fn enforce_edge_walls(maz: &mut Vec<Vec<char>>) {
+    fn enforce_edge_walls(maz: &mut [Vec<char>]) {
         let row = maz.len();
         let col = maz[0].len();
 
-        for r in 0..row {
-            for c in 0..col {
+        for (r, line) in maz.iter_mut().enumerate() {
+            for (c, tile) in line.iter_mut().enumerate() {
                 if r == 0 || r == row - 1 || c == 0 || c == col - 1 {
-                    maz[r][c] = '#';
+                    *tile = '#';
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Backtracker;
+
+impl Backtracker {
+    // Depth first carving, which gives long winding corridors with few branches
+    pub fn with_seed(row: usize, col: usize, seed: u64) -> Vec<Vec<char>> {
+        let (row, col) = (row.max(MIN_SIZE), col.max(MIN_SIZE));
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut maz = vec![vec!['#'; col]; row];
+
+        // Cells sit on odd coordinates, with the walls between them on even ones
+        let cells_r = (row.saturating_sub(1) / 2).max(1);
+        let cells_c = (col.saturating_sub(1) / 2).max(1);
+        let start = (1 + 2 * rng.gen_range(0..cells_r), 1 + 2 * rng.gen_range(0..cells_c));
+        maz[start.0][start.1] = '.';
+
+        let mut stack = vec![start];
+        while let Some(&(r, c)) = stack.last() {
+            let unvisited: Vec<(usize, usize)> = [(-2, 0), (2, 0), (0, -2), (0, 2)]
+                .iter()
+                .map(|(dr, dc)| (r as isize + dr, c as isize + dc))
+                .filter(|&(new_r, new_c)| {
+                    new_r > 0 && new_c > 0 &&
+                    new_r < row as isize - 1 && new_c < col as isize - 1 &&
+                    maz[new_r as usize][new_c as usize] == '#'
+                })
+                .map(|(new_r, new_c)| (new_r as usize, new_c as usize))
+                .collect();
+
+            match unvisited.choose(&mut rng) {
+                Some(&(new_r, new_c)) => {
+                    maz[(r + new_r) / 2][(c + new_c) / 2] = '.';
+                    maz[new_r][new_c] = '.';
+                    stack.push((new_r, new_c));
+                }
+                None => {
+                    stack.pop();
+                }
+            }
+        }
+
+        // Start and end are doors in the outer wall, like in Prim's mazes
+        let doors: Vec<((usize, usize), (usize, usize))> = (0..row)
+            .flat_map(|r| (0..col).map(move |c| (r, c)))
+            .filter_map(|(r, c)| {
+                let inner = match (r, c) {
+                    (0, c) if c > 0 && c < col - 1 => (1, c),
+                    (r, c) if r == row - 1 && c > 0 && c < col - 1 => (r - 1, c),
+                    (r, 0) if r > 0 && r < row - 1 => (r, 1),
+                    (r, c) if c == col - 1 && r > 0 && r < row - 1 => (r, c - 1),
+                    _ => return None,
+                };
+                (maz[inner.0][inner.1] == '.').then_some(((r, c), inner))
+            })
+            .collect();
+
+        if let Some(&((sr, sc), _)) = doors.choose(&mut rng) {
+            maz[sr][sc] = 'S';
+
+            // Put the exit as far from the start as the corridors allow
+            let dist = distances_from(&maz, (sr, sc));
+            if let Some(&((er, ec), _)) = doors
+                .iter()
+                .filter(|(door, _)| *door != (sr, sc))
+                .max_by_key(|(_, inner)| dist[inner.0][inner.1])
+            {
+                maz[er][ec] = 'E';
+            }
+        }
+
+        maz
+    }
+}
+
+// Opens a wall at roughly `fraction` of the dead ends, turning them into loops
+pub fn braid(maz: &mut [Vec<char>], fraction: f32, seed: u64) {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let row = maz.len();
+    let col = maz.first().map_or(0, |line| line.len());
+    let tile = |maz: &[Vec<char>], r: isize, c: isize| {
+        if r < 0 || c < 0 {
+            return None;
+        }
+        maz.get(r as usize).and_then(|line| line.get(c as usize)).copied()
+    };
+    let exits = |maz: &[Vec<char>], r: usize, c: usize| {
+        [(-1, 0), (1, 0), (0, -1), (0, 1)]
+            .iter()
+            .filter(|(dr, dc)| tile(maz, r as isize + dr, c as isize + dc).is_some_and(|t| t != '#'))
+            .count()
+    };
+
+    let mut dead_ends: Vec<(usize, usize)> = (0..row)
+        .flat_map(|r| (0..col).map(move |c| (r, c)))
+        .filter(|&(r, c)| tile(maz, r as isize, c as isize) == Some('.') && exits(maz, r, c) == 1)
+        .collect();
+    dead_ends.shuffle(&mut rng);
+
+    let count = (dead_ends.len() as f32 * fraction.clamp(0.0, 1.0)).round() as usize;
+    for (r, c) in dead_ends.into_iter().take(count) {
+        // An earlier opening may already have joined this one up
+        if exits(maz, r, c) != 1 {
+            continue;
+        }
+
+        // Knock through to floor on the far side, never into the outer wall
+        let walls: Vec<(usize, usize)> = [(-1, 0), (1, 0), (0, -1), (0, 1)]
+            .iter()
+            .filter(|(dr, dc)| {
+                let (wall_r, wall_c) = (r as isize + dr, c as isize + dc);
+                wall_r > 0 && wall_c > 0 &&
+                wall_r < row as isize - 1 && wall_c < col as isize - 1 &&
+                tile(maz, wall_r, wall_c) == Some('#') &&
+                tile(maz, wall_r + dr, wall_c + dc).is_some_and(|t| t != '#')
+            })
+            .map(|(dr, dc)| ((r as isize + dr) as usize, (c as isize + dc) as usize))
+            .collect();
+
+        if let Some(&(wall_r, wall_c)) = walls.choose(&mut rng) {
+            maz[wall_r][wall_c] = '.';
+        }
+    }
+}
+
+// Shortest S -> E route as (row, col) tiles, found with a breadth first search
+pub fn solve(maz: &[Vec<char>]) -> Option<Vec<(usize, usize)>> {
+    let start = find_tile(maz, 'S')?;
+    let end = find_tile(maz, 'E')?;
+
+    // Sized from the longest row, so hand-edited maps with ragged rows still solve
+    let row = maz.len();
+    let col = maz.iter().map(|line| line.len()).max().unwrap_or(0);
+    let mut parent: Vec<Vec<Option<(usize, usize)>>> = vec![vec![None; col]; row];
+    let mut queue = VecDeque::from([start]);
+    parent[start.0][start.1] = Some(start);
+
+    while let Some((r, c)) = queue.pop_front() {
+        if (r, c) == end {
+            let mut path = vec![end];
+            let mut curr = end;
+            while curr != start {
+                curr = parent[curr.0][curr.1]?;
+                path.push(curr);
+            }
+            path.reverse();
+            return Some(path);
+        }
+
+        for (dr, dc) in [(-1, 0), (1, 0), (0, -1), (0, 1)] {
+            let new_r = (r as isize + dr) as usize;
+            let new_c = (c as isize + dc) as usize;
+
+            if maz.get(new_r).and_then(|line| line.get(new_c)).is_some_and(|&t| t != '#') &&
+               parent[new_r][new_c].is_none() {
+                parent[new_r][new_c] = Some((r, c));
+                queue.push_back((new_r, new_c));
+            }
+        }
+    }
+
+    None
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Stats {
+    pub rows: usize,
+    pub cols: usize,
+    pub walls: usize,
+    pub floor: usize,
+    // Floor tiles connected to the start
+    pub reachable: usize,
+    pub dead_ends: usize,
+    pub junctions: usize,
+    // Steps along the shortest S -> E route, if there is one
+    pub solution_length: Option<usize>,
+    pub solution_turns: Option<usize>,
+}
+
+pub fn analyze(maz: &[Vec<char>]) -> Stats {
+    let row = maz.len();
+    let col = maz.iter().map(|line| line.len()).max().unwrap_or(0);
+    let open = |r: isize, c: isize| {
+        r >= 0 && c >= 0 &&
+        maz.get(r as usize).and_then(|line| line.get(c as usize)).is_some_and(|&t| t != '#')
+    };
+
+    let mut stats = Stats { rows: row, cols: col, ..Default::default() };
+    for (r, line) in maz.iter().enumerate() {
+        for (c, &tile) in line.iter().enumerate() {
+            if tile == '#' {
+                stats.walls += 1;
+                continue;
+            }
+            stats.floor += 1;
+
+            let exits = [(-1, 0), (1, 0), (0, -1), (0, 1)]
+                .iter()
+                .filter(|(dr, dc)| open(r as isize + dr, c as isize + dc))
+                .count();
+            match exits {
+                1 if tile == '.' => stats.dead_ends += 1,
+                3 | 4 => stats.junctions += 1,
+                _ => {}
+            }
+        }
+    }
+
+    if let Some((sr, sc)) = find_tile(maz, 'S') {
+        let mut visited = vec![vec![false; col]; row];
+        let mut stack = vec![(sr, sc)];
+        visited[sr][sc] = true;
+        while let Some((r, c)) = stack.pop() {
+            stats.reachable += 1;
+            for (dr, dc) in [(-1, 0), (1, 0), (0, -1), (0, 1)] {
+                let (new_r, new_c) = (r as isize + dr, c as isize + dc);
+                if open(new_r, new_c) && !visited[new_r as usize][new_c as usize] {
+                    visited[new_r as usize][new_c as usize] = true;
+                    stack.push((new_r as usize, new_c as usize));
                 }
             }
         }
     }
+
+    if let Some(path) = solve(maz) {
+        stats.solution_length = Some(path.len() - 1);
+        stats.solution_turns = Some(path.windows(3).filter(|w| {
+            (w[1].0 as isize - w[0].0 as isize, w[1].1 as isize - w[0].1 as isize) !=
+            (w[2].0 as isize - w[1].0 as isize, w[2].1 as isize - w[1].1 as isize)
+        }).count());
+    }
+
+    stats
+}
+
+// Number of steps from `from` to every floor tile, `None` where unreachable
+pub fn distances_from(maz: &[Vec<char>], from: (usize, usize)) -> Vec<Vec<Option<usize>>> {
+    let mut dist: Vec<Vec<Option<usize>>> = maz.iter().map(|line| vec![None; line.len()]).collect();
+    let mut queue = VecDeque::from([from]);
+    dist[from.0][from.1] = Some(0);
+
+    while let Some((r, c)) = queue.pop_front() {
+        let d = dist[r][c].unwrap_or(0);
+        for (dr, dc) in [(-1, 0), (1, 0), (0, -1), (0, 1)] {
+            let new_r = (r as isize + dr) as usize;
+            let new_c = (c as isize + dc) as usize;
+
+            if maz.get(new_r).and_then(|line| line.get(new_c)).is_some_and(|&t| t != '#') &&
+               dist[new_r][new_c].is_none() {
+                dist[new_r][new_c] = Some(d + 1);
+                queue.push_back((new_r, new_c));
+            }
+        }
+    }
+
+    dist
 }
 
+// Tiles within `radius` of `from` that can be seen from its centre. Walls count as
+// seen when they touch a floor tile that is, since their faces are in view from there
+pub fn visible_from(maz: &[Vec<char>], from: (usize, usize), radius: usize) -> Vec<(usize, usize)> {
+    let is_wall = |r: usize, c: usize| maz.get(r).and_then(|line| line.get(c)) == Some(&'#');
+    let is_floor = |r: usize, c: usize| maz.get(r).and_then(|line| line.get(c)).is_some_and(|&t| t != '#');
+    let mut seen = Vec::new();
+    let last_r = (from.0 + radius).min(maz.len().saturating_sub(1));
+
+    for r in from.0.saturating_sub(radius)..=last_r {
+        let last_c = (from.1 + radius).min(maz.get(r).map_or(0, Vec::len).saturating_sub(1));
+        for c in from.1.saturating_sub(radius)..=last_c {
+            if is_floor(r, c) && line_of_sight(maz, from, (r, c)) {
+                seen.push((r, c));
+            }
+        }
+    }
+
+    let mut walls = Vec::new();
+    for &(r, c) in &seen {
+        for nr in r.saturating_sub(1)..=r + 1 {
+            for nc in c.saturating_sub(1)..=c + 1 {
+                if is_wall(nr, nc) {
+                    walls.push((nr, nc));
+                }
+            }
+        }
+    }
+    walls.sort_unstable();
+    walls.dedup();
+    seen.extend(walls);
+
+    seen
+}
+
+// Steps along the straight line between two tile centres in quarter tiles,
+// false if it passes through a wall on the way
+fn line_of_sight(maz: &[Vec<char>], from: (usize, usize), to: (usize, usize)) -> bool {
+    let dr = to.0 as f32 - from.0 as f32;
+    let dc = to.1 as f32 - from.1 as f32;
+    let steps = (dr.abs().max(dc.abs()) * 4.0) as usize;
+
+    for i in 1..steps {
+        let t = i as f32 / steps as f32;
+        let r = (from.0 as f32 + dr * t).round() as usize;
+        let c = (from.1 as f32 + dc * t).round() as usize;
+        if (r, c) == to {
+            return true;
+        }
+        if maz.get(r).and_then(|line| line.get(c)).is_none_or(|&t| t == '#') {
+            return false;
+        }
+    }
+
+    true
+}
+
+pub fn find_tile(maz: &[Vec<char>], tile: char) -> Option<(usize, usize)> {
+    maz.iter().enumerate().find_map(|(r, line)| {
+        line.iter().position(|&t| t == tile).map(|c| (r, c))
+    })
+}
 
 #[derive(Debug, Clone, Default)]
 struct Point {
@@ -217,4 +583,94 @@ impl Point {
         }
         None
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{generated, grid};
+
+    #[test]
+    fn solves_shortest_route() {
+        let maz = grid("S.#\n..#\n#.E");
+        assert_eq!(solve(&maz), Some(vec![(0, 0), (1, 0), (1, 1), (2, 1), (2, 2)]));
+        assert_eq!(solve(&grid("S#E")), None);
+        assert_eq!(solve(&grid("S..")), None);
+    }
+
+    #[test]
+    fn solves_ragged_grids() {
+        // A trailing blank line, as left behind by an editor
+        let mut maz = grid("S..\n...\n..E");
+        maz.push(Vec::new());
+        assert_eq!(solve(&maz).map(|path| path.len()), Some(5));
+
+        // Short rows in the middle and a long one at the end
+        let maz = grid("S..\n#\n....E");
+        assert_eq!(solve(&maz), None);
+        let maz = grid("S\n.\n....E");
+        assert_eq!(solve(&maz).map(|path| path.len()), Some(7));
+    }
+
+    #[test]
+    fn generates_solvable_mazes_of_any_size() {
+        let sizes = [(0, 0), (1, 1), (1, 9), (2, 2), (3, 3), (4, 7), (21, 21), (30, 11)];
+        for g in generated(&sizes, 0..20) {
+            assert_eq!(g.maz.len(), g.row.max(MIN_SIZE), "{}", g.label());
+            assert!(g.maz.iter().all(|line| line.len() == g.col.max(MIN_SIZE)), "{}", g.label());
+            assert!(solve(&g.maz).is_some(), "{}", g.label());
+        }
+    }
+
+    #[test]
+    fn sees_open_floor_within_radius() {
+        let maz = grid(".......\n.......\n.......");
+        let seen = visible_from(&maz, (1, 3), 2);
+        assert_eq!(seen.len(), 3 * 5);
+        assert!(seen.contains(&(0, 1)) && seen.contains(&(2, 5)));
+        assert!(!seen.contains(&(1, 0)) && !seen.contains(&(1, 6)));
+    }
+
+    #[test]
+    fn sees_nothing_outside_the_maze() {
+        assert!(visible_from(&[], (0, 0), 8).is_empty());
+        assert!(visible_from(&[Vec::new(), Vec::new()], (1, 0), 8).is_empty());
+        assert!(visible_from(&grid("..\n.."), (40, 40), 8).is_empty());
+        // A short row doesn't cut off the longer row the viewer stands in
+        assert_eq!(visible_from(&grid(".\n..."), (1, 2), 8), vec![(1, 0), (1, 1), (1, 2)]);
+    }
+
+    #[test]
+    fn walls_block_the_view() {
+        let maz = grid("#######\n#..#..#\n#######");
+        let seen = visible_from(&maz, (1, 1), 8);
+
+        // The near room and the walls around it
+        for tile in [(1, 1), (1, 2), (0, 0), (0, 3), (1, 3), (2, 3)] {
+            assert!(seen.contains(&tile), "{:?}", tile);
+        }
+        // Nothing behind the dividing wall
+        for tile in [(1, 4), (1, 5), (0, 4), (1, 6)] {
+            assert!(!seen.contains(&tile), "{:?}", tile);
+        }
+    }
+
+    #[test]
+    fn line_of_sight_around_corners() {
+        let maz = grid("...\n.#.\n...");
+        assert!(line_of_sight(&maz, (0, 0), (0, 2)));
+        assert!(line_of_sight(&maz, (0, 0), (2, 0)));
+        assert!(!line_of_sight(&maz, (0, 0), (2, 2)));
+        assert!(!line_of_sight(&maz, (1, 0), (1, 2)));
+        // Neighbours are always in view, even diagonally past a wall
+        assert!(line_of_sight(&maz, (0, 1), (1, 2)));
+        // Leaving the grid counts as blocked
+        assert!(!line_of_sight(&grid("..\n."), (0, 0), (2, 1)));
+    }
+
+    #[test]
+    fn same_seed_same_maze() {
+        for g in generated(&[(15, 15)], 42..43) {
+            assert_eq!(g.algorithm.generate(g.row, g.col, g.seed), g.maz, "{}", g.label());
+        }
+    }
+}
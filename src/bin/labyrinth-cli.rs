@@ -0,0 +1,173 @@
+use std::{error::Error, io::{self, Write}, path::PathBuf, process::ExitCode};
+use clap::{Parser, Subcommand};
+use labyrinth::{algo::{self, Algorithm}, code, export::{self, ExportOptions}, map};
+
+/// Generate, solve and render labyrinth mazes without starting the game
+#[derive(Parser)]
+#[command(name = "labyrinth-cli", version)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Generate a new maze
+    Generate {
+        #[arg(long, default_value = "prim")]
+        algo: Algorithm,
+        /// Maze size as ROWSxCOLS
+        #[arg(long, default_value = "20x20", value_parser = parse_size)]
+        size: (usize, usize),
+        /// Seed for reproducible mazes, random when omitted
+        #[arg(long)]
+        seed: Option<u64>,
+        /// Percentage of dead ends to open up into loops
+        #[arg(long, default_value_t = 0, value_parser = clap::value_parser!(u32).range(0..=100))]
+        braid: u32,
+        /// Output tilemap file, stdout when omitted
+        #[arg(long)]
+        out: Option<PathBuf>,
+    },
+    /// Print a maze with its shortest S -> E route drawn in `o`
+    Solve {
+        input: PathBuf,
+        #[arg(long)]
+        out: Option<PathBuf>,
+    },
+    /// Print size, dead end and solution statistics for a maze
+    Analyze {
+        input: PathBuf,
+    },
+    /// Render a maze to SVG or PNG
+    Render {
+        input: PathBuf,
+        #[arg(long, conflicts_with = "png")]
+        svg: bool,
+        #[arg(long)]
+        png: bool,
+        /// Output image file, stdout when omitted (SVG only)
+        #[arg(long)]
+        out: Option<PathBuf>,
+        /// Side length of one tile in pixels
+        #[arg(long, default_value_t = 16)]
+        cell_size: u32,
+        /// Overlay the shortest S -> E route
+        #[arg(long)]
+        solution: bool,
+        /// Leave the start and end tiles uncoloured
+        #[arg(long)]
+        no_markers: bool,
+    },
+    /// Print the shareable maze code for a maze
+    Encode {
+        input: PathBuf,
+    },
+    /// Turn a maze code back into a tilemap
+    Decode {
+        code: String,
+        #[arg(long)]
+        out: Option<PathBuf>,
+    },
+}
+
+fn main() -> ExitCode {
+    match run(Cli::parse().command) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(command: Command) -> Result<(), Box<dyn Error>> {
+    match command {
+        Command::Generate { algo, size: (row, col), seed, braid, out } => {
+            let seed = seed.unwrap_or_else(rand::random);
+            let mut maz = algo.generate(row, col, seed);
+            algo::braid(&mut maz, braid as f32 / 100.0, seed);
+            eprintln!("{} {}x{} seed {}", algo, row, col, seed);
+            write_maze(&maz, out)
+        }
+        Command::Solve { input, out } => {
+            let mut maz = map::load(&input)?;
+            let path = algo::solve(&maz).ok_or("maze has no route from S to E")?;
+            for &(r, c) in path.iter().skip(1).take(path.len().saturating_sub(2)) {
+                maz[r][c] = 'o';
+            }
+            write_maze(&maz, out)
+        }
+        Command::Analyze { input } => {
+            let maz = map::load(&input)?;
+            let stats = algo::analyze(&maz);
+            let percent = |n: usize| 100.0 * n as f32 / stats.floor.max(1) as f32;
+
+            println!("size:       {}x{}", stats.rows, stats.cols);
+            println!("walls:      {}", stats.walls);
+            println!("floor:      {}", stats.floor);
+            println!("reachable:  {} ({:.1}%)", stats.reachable, percent(stats.reachable));
+            println!("dead ends:  {}", stats.dead_ends);
+            println!("junctions:  {}", stats.junctions);
+            match (stats.solution_length, stats.solution_turns) {
+                (Some(len), Some(turns)) => {
+                    println!("solution:   {} steps, {} turns ({:.1}% of floor)", len, turns, percent(len + 1));
+                }
+                _ => println!("solution:   none"),
+            }
+            Ok(())
+        }
+        Command::Render { input, svg, png, out, cell_size, solution, no_markers } => {
+            let maz = map::load(&input)?;
+            let options = ExportOptions { cell_size, solution, markers: !no_markers };
+
+            // Fall back to the output extension when no format flag is given
+            let png = png || (!svg && out.as_ref().is_some_and(|p| p.extension().is_some_and(|e| e == "png")));
+            match (png, out) {
+                #[cfg(feature = "image")]
+                (true, Some(out)) => export::save_png(&maz, out, &options)?,
+                #[cfg(not(feature = "image"))]
+                (true, Some(_)) => return Err("PNG output needs the `image` feature".into()),
+                (true, None) => return Err("--png needs --out".into()),
+                (false, Some(out)) => export::save_svg(&maz, out, &options)?,
+                (false, None) => io::stdout().write_all(export::to_svg(&maz, &options).as_bytes())?,
+            }
+            Ok(())
+        }
+        Command::Encode { input } => {
+            let maz = map::load(&input)?;
+            println!("{}", code::to_code(&maz)?);
+            Ok(())
+        }
+        Command::Decode { code, out } => {
+            let maz = code::from_code(&code)?;
+            write_maze(&maz, out)
+        }
+    }
+}
+
+fn write_maze(maz: &[Vec<char>], out: Option<PathBuf>) -> Result<(), Box<dyn Error>> {
+    match out {
+        Some(path) => map::save(maz, path)?,
+        None => {
+            let text: String = maz.iter().map(|row| row.iter().collect::<String>() + "\n").collect();
+            io::stdout().write_all(text.as_bytes())?;
+        }
+    }
+    Ok(())
+}
+
+fn parse_size(s: &str) -> Result<(usize, usize), String> {
+    let (row, col) = s
+        .split_once(['x', 'X'])
+        .ok_or_else(|| format!("expected ROWSxCOLS, got `{}`", s))?;
+    let row: usize = row.trim().parse().map_err(|_| format!("bad row count `{}`", row))?;
+    let col: usize = col.trim().parse().map_err(|_| format!("bad column count `{}`", col))?;
+    if row < algo::MIN_SIZE || col < algo::MIN_SIZE {
+        return Err(format!("mazes need at least {} rows and {} columns", algo::MIN_SIZE, algo::MIN_SIZE));
+    }
+    if row > u16::MAX as usize || col > u16::MAX as usize {
+        return Err(format!("mazes can be at most {}x{}", u16::MAX, u16::MAX));
+    }
+    Ok((row, col))
+}
@@ -1,65 +1,359 @@
-use bevy::prelude::*;
+use bevy::{
+    input::mouse::MouseMotion,
+    prelude::*,
+    render::{camera::{RenderTarget, ScalingMode}, view::RenderLayers},
+    window::{CursorGrabMode, PrimaryWindow},
+};
+use bevy_rapier3d::prelude::*;
 use bevy_third_person_camera::*;
-use crate::menu::GameState;
+use serde::{Deserialize, Serialize};
+use crate::{
+    input::{Action, Actions, GamepadInput, STICK_DEADZONE},
+    menu::{GameState, PauseState},
+    minimap::{MapTexture, MapView, MAP_LAYER},
+    player::{Flashlight, Player},
+    settings::GameSettings,
+    world::{Maze, MazeLoaded},
+};
+
+// The floor is flat, so the eyes sit at a fixed height, a little under the wall tops
+pub const EYE_HEIGHT: f32 = 1.2;
+const BOB_AMPLITUDE: f32 = 0.04;
+// Radians of the bob cycle per unit walked
+const BOB_RATE: f32 = 6.0;
+const MOUSE_LOOK_SPEED: f32 = 0.003;
+// Radians per second at full stick
+const STICK_LOOK_SPEED: f32 = 2.5;
+const MAX_PITCH: f32 = 1.5;
+// Size of the sphere swept from the player to the third person camera, keeps the near plane out of walls
+const COLLISION_RADIUS: f32 = 0.2;
+// How quickly the camera moves back out once a wall is no longer in the way
+const COLLISION_EASE_OUT: f32 = 4.0;
 
 pub struct CameraPlugin;
 
 impl Plugin for CameraPlugin {
     fn build(&self, app: &mut App) {
         app
-            .add_systems(OnEnter(GameState::InGame), spawn_camera)
+            .add_systems(OnEnter(GameState::InGame), (spawn_camera, spawn_map_camera.after(MazeLoaded)))
+            .add_systems(Update, (select_gamepad, update_map_camera))
+            .add_systems(Update, (
+                (toggle_camera_mode, first_person_look).run_if(in_state(PauseState::Running)),
+                (apply_camera_mode, follow_player_first_person, first_person_cursor, sync_player_model)
+                    .chain()
+                    .run_if(in_state(GameState::InGame)),
+            ).chain())
+            // Before physics moves the player, while the camera still sits at its zoom distance from them
+            .add_systems(PostUpdate, avoid_walls
+                .before(PhysicsSet::SyncBackend)
+                .run_if(in_state(GameState::InGame))
+                .run_if(not(in_state(PauseState::Flythrough))))
             .add_systems(OnExit(GameState::InGame), despawn_camera);
-            
     }
 }
 
-// #[derive(Component)]
-// struct FirstPersonCamera {
-//     yaw: f32,
-//     pitch: f32,
-// }
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CameraMode {
+    #[default]
+    ThirdPerson,
+    FirstPerson,
+}
+
+impl CameraMode {
+    pub fn toggled(self) -> CameraMode {
+        match self {
+            CameraMode::ThirdPerson => CameraMode::FirstPerson,
+            CameraMode::FirstPerson => CameraMode::ThirdPerson,
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct GameCamera;
 
+// Looks straight down at the flat map and renders it into `MapTexture` for the UI
 #[derive(Component)]
-struct GameCamera;
-
-
-// fn spawn_first_person_camera(mut commands: Commands) {
-//     commands.spawn((
-//         Camera3dBundle {
-//             transform: Transform::from_xyz(0.0, 1.7, 0.0), // Typical eye height
-//             camera: Camera {
-//                 order: 0, // Set a lower order than the UI camera
-//                 ..default()
-//             },
-//             ..default()
-//         },
-//         FirstPersonCamera {
-//             yaw: 0.0,
-//             pitch: 0.0,
-//         },
-//     ));
-// }
+struct MapCamera;
+
+// How far the third person camera sits from the player after being pulled in front of walls
+#[derive(Component)]
+struct CameraCollision {
+    distance: f32,
+}
+
+#[derive(Component, Default)]
+struct FirstPersonCamera {
+    yaw: f32,
+    pitch: f32,
+    // Distance walked, in radians of the head bob cycle
+    bob_phase: f32,
+    // Fades the bob in and out as the player starts and stops
+    bob_weight: f32,
+}
 
 fn spawn_camera(
     mut commands: Commands,
+    settings: Res<GameSettings>,
 ) {
     let camera = (
         Camera3dBundle {
             transform: Transform::from_xyz(-2.0, 2.5, 5.0).looking_at(Vec3::ZERO, Vec3::Y),
             ..Default::default()
         },
-        ThirdPersonCamera{
-            zoom: Zoom::new(1.0, 3.0),
-            ..default()
-        },
         GameCamera,
+        CameraCollision { distance: f32::MAX },
     );
 
-    commands.spawn(camera);
+    let mut camera = commands.spawn(camera);
+    match settings.camera {
+        CameraMode::ThirdPerson => camera.insert(third_person_camera(&settings)),
+        CameraMode::FirstPerson => camera.insert(FirstPersonCamera::default()),
+    };
+}
+
+fn spawn_map_camera(
+    mut commands: Commands,
+    maze: Res<Maze>,
+    view: Res<MapView>,
+    map_texture: Res<MapTexture>,
+) {
+    let m = maze.0.len() as f32;
+    let n = maze.0.iter().map(|line| line.len()).max().unwrap_or(0) as f32;
+    let center = Vec3::new(n/2.0 - 0.5, 0.0, m/2.0 - 0.5);
+
+    commands.spawn((
+        Camera3dBundle {
+            camera: Camera {
+                // Before the game camera, so the UI showing the map gets this frame's image
+                order: -1,
+                target: RenderTarget::Image(map_texture.0.clone()),
+                clear_color: ClearColorConfig::Custom(Color::srgb(0.05, 0.05, 0.05)),
+                is_active: *view != MapView::Hidden,
+                ..default()
+            },
+            // Fits the whole maze whatever its size, with half a tile to spare around it
+            projection: OrthographicProjection {
+                scaling_mode: ScalingMode::AutoMin {
+                    min_width: n + 1.0,
+                    min_height: m + 1.0,
+                },
+                ..default()
+            }.into(),
+            // Row 0 at the top of the map, like the map file
+            transform: Transform::from_translation(center + Vec3::Y * 10.0).looking_at(center, Vec3::NEG_Z),
+            ..default()
+        },
+        RenderLayers::layer(MAP_LAYER),
+        MapCamera,
+        Name::new("Map Camera"),
+    ));
+}
+
+// No need to keep rendering the map while it is hidden
+fn update_map_camera(view: Res<MapView>, mut cam_q: Query<&mut Camera, With<MapCamera>>) {
+    if !view.is_changed() {
+        return;
+    }
+    for mut camera in cam_q.iter_mut() {
+        camera.is_active = *view != MapView::Hidden;
+    }
+}
+
+fn third_person_camera(settings: &GameSettings) -> ThirdPersonCamera {
+    ThirdPersonCamera {
+        zoom: Zoom::new(settings.zoom_min, settings.zoom_max),
+        sensitivity: Vec2::splat(settings.mouse_sensitivity),
+        ..default()
+    }
+}
+
+fn toggle_camera_mode(
+    actions: Actions,
+    mut settings: ResMut<GameSettings>,
+) {
+    if actions.just_pressed(Action::ToggleCamera) {
+        settings.camera = settings.camera.toggled();
+    }
+}
+
+// Swaps the camera components whenever the mode setting changes, from the key or the settings menu
+fn apply_camera_mode(
+    mut commands: Commands,
+    settings: Res<GameSettings>,
+    cam_q: Query<(Entity, &Transform, Has<FirstPersonCamera>), With<GameCamera>>,
+) {
+    if !settings.is_changed() {
+        return;
+    }
+
+    for (entity, transform, is_first_person) in cam_q.iter() {
+        match (settings.camera, is_first_person) {
+            (CameraMode::FirstPerson, false) => {
+                // Keep facing the same way, but level
+                let (yaw, _, _) = transform.rotation.to_euler(EulerRot::YXZ);
+                commands
+                    .entity(entity)
+                    .remove::<ThirdPersonCamera>()
+                    .insert(FirstPersonCamera { yaw, ..default() });
+            }
+            (CameraMode::ThirdPerson, true) => {
+                commands
+                    .entity(entity)
+                    .remove::<FirstPersonCamera>()
+                    .insert(third_person_camera(&settings));
+            }
+            _ => {}
+        }
+    }
 }
 
-fn despawn_camera(mut commands: Commands, query: Query<Entity, With<GameCamera>>) {
+// Sweeps a small sphere from the player back to where the camera crate put the camera,
+// and moves the camera in front of the first wall it hits
+// The player the third person camera orbits, kept apart from the camera's own Transform
+type CameraTarget = (With<ThirdPersonCameraTarget>, Without<ThirdPersonCamera>);
+
+fn avoid_walls(
+    time: Res<Time>,
+    rapier: Res<RapierContext>,
+    player_q: Query<(Entity, &Transform), CameraTarget>,
+    mut cam_q: Query<(&mut Transform, &mut CameraCollision), With<ThirdPersonCamera>>,
+) {
+    let Ok((player, target)) = player_q.get_single() else {
+        return;
+    };
+
+    for (mut transform, mut collision) in cam_q.iter_mut() {
+        let back = transform.rotation * Vec3::Z;
+        let wanted = transform.translation.distance(target.translation);
+        let hit = rapier.cast_shape(
+            target.translation,
+            Quat::IDENTITY,
+            back,
+            &Collider::ball(COLLISION_RADIUS),
+            ShapeCastOptions::with_max_time_of_impact(wanted),
+            QueryFilter::new().exclude_sensors().exclude_collider(player),
+        );
+        let clear = hit.map_or(wanted, |(_, hit)| hit.time_of_impact);
+
+        // Jump in straight away so the player is never hidden, but ease back out so the camera doesn't pop
+        collision.distance = match clear < collision.distance {
+            true => clear,
+            false => collision.distance + (clear - collision.distance) * (COLLISION_EASE_OUT * time.delta_seconds()).min(1.0),
+        };
+        transform.translation = target.translation + back * collision.distance;
+    }
+}
+
+fn first_person_look(
+    time: Res<Time>,
+    settings: Res<GameSettings>,
+    gamepad: GamepadInput,
+    mut motion: EventReader<MouseMotion>,
+    mut cam_q: Query<&mut FirstPersonCamera>,
+) {
+    let mut delta = motion.read().map(|m| m.delta).sum::<Vec2>() * MOUSE_LOOK_SPEED;
+    let stick = gamepad.right_stick();
+    if stick.length() > STICK_DEADZONE {
+        delta += Vec2::new(stick.x, -stick.y) * STICK_LOOK_SPEED * time.delta_seconds();
+    }
+    delta *= settings.mouse_sensitivity;
+
+    for mut cam in cam_q.iter_mut() {
+        cam.yaw -= delta.x;
+        cam.pitch = (cam.pitch - delta.y).clamp(-MAX_PITCH, MAX_PITCH);
+    }
+}
+
+fn follow_player_first_person(
+    time: Res<Time>,
+    player_q: Query<(&Transform, Option<&KinematicCharacterControllerOutput>), With<Player>>,
+    mut cam_q: Query<(&mut Transform, &mut FirstPersonCamera), Without<Player>>,
+) {
+    let Ok((player, output)) = player_q.get_single() else {
+        return;
+    };
+    let walked = output.map_or(0.0, |out| out.effective_translation.xz().length());
+
+    for (mut transform, mut cam) in cam_q.iter_mut() {
+        // Bob only while walking, settling back to eye height when the player stops
+        let target = if walked > 0.001 { 1.0 } else { 0.0 };
+        cam.bob_weight += (target - cam.bob_weight) * (8.0 * time.delta_seconds()).min(1.0);
+        cam.bob_phase = (cam.bob_phase + walked * BOB_RATE) % std::f32::consts::TAU;
+        let bob = cam.bob_phase.sin() * BOB_AMPLITUDE * cam.bob_weight;
+
+        transform.translation = Vec3::new(player.translation.x, EYE_HEIGHT + bob, player.translation.z);
+        transform.rotation = Quat::from_euler(EulerRot::YXZ, cam.yaw, cam.pitch, 0.0);
+    }
+}
+
+// The third person camera grabs the cursor itself, this does the same for first person
+fn first_person_cursor(
+    pause_state: Res<State<PauseState>>,
+    cam_q: Query<(), With<FirstPersonCamera>>,
+    mut window_q: Query<&mut Window, With<PrimaryWindow>>,
+) {
+    if cam_q.is_empty() {
+        return;
+    }
+    let Ok(mut window) = window_q.get_single_mut() else {
+        return;
+    };
+
+    let grab = *pause_state.get() == PauseState::Running;
+    let mode = if grab { CursorGrabMode::Locked } else { CursorGrabMode::None };
+    if window.cursor.grab_mode != mode {
+        window.cursor.grab_mode = mode;
+        window.cursor.visible = !grab;
+    }
+}
+
+// The player's own body would fill the view in first person, so hide it but keep the flashlight
+fn sync_player_model(
+    cam_q: Query<(), With<FirstPersonCamera>>,
+    player_q: Query<&Children, With<Player>>,
+    mut visibility_q: Query<&mut Visibility, Without<Flashlight>>,
+) {
+    let visibility = match cam_q.is_empty() {
+        true => Visibility::Inherited,
+        false => Visibility::Hidden,
+    };
+    for children in player_q.iter() {
+        for &child in children.iter() {
+            if let Ok(mut child_visibility) = visibility_q.get_mut(child) {
+                child_visibility.set_if_neq(visibility);
+            }
+        }
+    }
+}
+
+// The camera crate assumes the gamepad is always gamepad 0, point it at the one actually connected
+fn select_gamepad(
+    mut commands: Commands,
+    gamepads: Res<Gamepads>,
+    current: Option<Res<GamepadResource>>,
+) {
+    let Some(gamepad) = gamepads.iter().next() else {
+        return;
+    };
+    if current.is_none_or(|current| !gamepads.contains(current.0)) {
+        commands.insert_resource(GamepadResource(gamepad));
+    }
+}
+
+type AnyCamera = Or<(With<GameCamera>, With<MapCamera>)>;
+
+fn despawn_camera(
+    mut commands: Commands,
+    query: Query<Entity, AnyCamera>,
+    mut window_q: Query<&mut Window, With<PrimaryWindow>>,
+) {
     for entity in query.iter() {
         commands.entity(entity).despawn_recursive();
     }
+
+    // The third person camera locks the cursor, give it back for the menus
+    if let Ok(mut window) = window_q.get_single_mut() {
+        window.cursor.grab_mode = CursorGrabMode::None;
+        window.cursor.visible = true;
+    }
 }
\ No newline at end of file
@@ -0,0 +1,178 @@
+use std::fmt;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use crate::algo::find_tile;
+
+// Bumped whenever the packed layout changes
+const VERSION: u8 = 1;
+
+// version + width + height + start (r, c) + end (r, c)
+const HEADER_LEN: usize = 1 + 2 * 6;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CodeError {
+    InvalidBase64,
+    UnsupportedVersion(u8),
+    Truncated,
+    InvalidMarker,
+    Empty,
+    NotRectangular,
+    TooLarge,
+    MissingMarker(char),
+}
+
+impl fmt::Display for CodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CodeError::InvalidBase64 => write!(f, "maze code is not valid base64"),
+            CodeError::UnsupportedVersion(v) => write!(f, "unsupported maze code version {}", v),
+            CodeError::Truncated => write!(f, "maze code is too short for its dimensions"),
+            CodeError::InvalidMarker => write!(f, "start or end marker lies outside the maze"),
+            CodeError::Empty => write!(f, "maze has no tiles"),
+            CodeError::NotRectangular => write!(f, "maze rows are not all the same length"),
+            CodeError::TooLarge => write!(f, "maze is larger than {}x{}", u16::MAX, u16::MAX),
+            CodeError::MissingMarker(tile) => write!(f, "maze has no `{}` tile", tile),
+        }
+    }
+}
+
+impl std::error::Error for CodeError {}
+
+/// Packs a tile grid into bytes: a small header followed by one bit per tile
+/// (1 = wall), row-major, most significant bit first.
+///
+/// Walls in a tilemap are whole tiles rather than edges between cells, so this
+/// stores a bit per tile instead of one per wall edge. The grid has to be a
+/// non-empty rectangle of at most 65535x65535 tiles with both an `S` and an `E`.
+pub fn pack(maz: &[Vec<char>]) -> Result<Vec<u8>, CodeError> {
+    let row = maz.len();
+    let col = maz.first().map_or(0, |r| r.len());
+    if row == 0 || col == 0 {
+        return Err(CodeError::Empty);
+    }
+    if maz.iter().any(|line| line.len() != col) {
+        return Err(CodeError::NotRectangular);
+    }
+    if row > u16::MAX as usize || col > u16::MAX as usize {
+        return Err(CodeError::TooLarge);
+    }
+
+    let (sr, sc) = find_tile(maz, 'S').ok_or(CodeError::MissingMarker('S'))?;
+    let (er, ec) = find_tile(maz, 'E').ok_or(CodeError::MissingMarker('E'))?;
+
+    let mut bytes = Vec::with_capacity(HEADER_LEN + (row * col).div_ceil(8));
+    bytes.push(VERSION);
+    for v in [col, row, sr, sc, er, ec] {
+        bytes.extend_from_slice(&(v as u16).to_be_bytes());
+    }
+
+    let mut acc = 0u8;
+    let mut n = 0;
+    for line in maz {
+        for &tile in line {
+            acc = (acc << 1) | (tile == '#') as u8;
+            n += 1;
+            if n == 8 {
+                bytes.push(acc);
+                acc = 0;
+                n = 0;
+            }
+        }
+    }
+    if n > 0 {
+        bytes.push(acc << (8 - n));
+    }
+
+    Ok(bytes)
+}
+
+/// Reverses `pack`, placing the start and end markers back on the grid.
+pub fn unpack(bytes: &[u8]) -> Result<Vec<Vec<char>>, CodeError> {
+    if bytes.len() < HEADER_LEN {
+        return Err(CodeError::Truncated);
+    }
+    if bytes[0] != VERSION {
+        return Err(CodeError::UnsupportedVersion(bytes[0]));
+    }
+
+    let field = |i: usize| u16::from_be_bytes([bytes[1 + 2 * i], bytes[2 + 2 * i]]) as usize;
+    let (col, row) = (field(0), field(1));
+    let (sr, sc, er, ec) = (field(2), field(3), field(4), field(5));
+
+    let body = &bytes[HEADER_LEN..];
+    if body.len() < (row * col).div_ceil(8) {
+        return Err(CodeError::Truncated);
+    }
+    if sr >= row || sc >= col || er >= row || ec >= col {
+        return Err(CodeError::InvalidMarker);
+    }
+
+    let mut maz = vec![vec!['.'; col]; row];
+    for (r, line) in maz.iter_mut().enumerate() {
+        for (c, tile) in line.iter_mut().enumerate() {
+            let i = r * col + c;
+            if body[i / 8] & (0x80 >> (i % 8)) != 0 {
+                *tile = '#';
+            }
+        }
+    }
+    maz[sr][sc] = 'S';
+    maz[er][ec] = 'E';
+
+    Ok(maz)
+}
+
+/// Encodes a maze as a URL-safe base64 string that can be pasted into chat.
+pub fn to_code(maz: &[Vec<char>]) -> Result<String, CodeError> {
+    Ok(URL_SAFE_NO_PAD.encode(pack(maz)?))
+}
+
+pub fn from_code(code: &str) -> Result<Vec<Vec<char>>, CodeError> {
+    let bytes = URL_SAFE_NO_PAD
+        .decode(code.trim())
+        .map_err(|_| CodeError::InvalidBase64)?;
+    unpack(&bytes)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{generated, grid};
+
+    #[test]
+    fn round_trips_generated_mazes() {
+        for g in generated(&[(5, 5), (20, 20), (21, 37), (51, 9)], 0..4) {
+            let code = to_code(&g.maz).unwrap();
+            assert_eq!(from_code(&code).unwrap(), g.maz, "{}", g.label());
+        }
+    }
+
+    #[test]
+    fn rejects_mazes_that_cannot_round_trip() {
+        assert_eq!(pack(&[]), Err(CodeError::Empty));
+        assert_eq!(pack(&grid("S..\n.\n..E")), Err(CodeError::NotRectangular));
+        assert_eq!(pack(&grid("###\n#.#\n###")), Err(CodeError::MissingMarker('S')));
+        assert_eq!(pack(&grid("S..\n...\n...")), Err(CodeError::MissingMarker('E')));
+
+        let wide = vec![vec!['.'; u16::MAX as usize + 1]];
+        assert_eq!(pack(&wide), Err(CodeError::TooLarge));
+    }
+
+    #[test]
+    fn rejects_corrupt_codes() {
+        assert_eq!(from_code("not a code!"), Err(CodeError::InvalidBase64));
+
+        let bytes = pack(&grid("S.#\n.#.\n#.E")).unwrap();
+
+        let mut bad_version = bytes.clone();
+        bad_version[0] = VERSION + 1;
+        assert_eq!(unpack(&bad_version), Err(CodeError::UnsupportedVersion(VERSION + 1)));
+
+        assert_eq!(unpack(&bytes[..HEADER_LEN - 1]), Err(CodeError::Truncated));
+        assert_eq!(unpack(&bytes[..bytes.len() - 1]), Err(CodeError::Truncated));
+
+        let mut bad_marker = bytes.clone();
+        bad_marker[5..7].copy_from_slice(&7u16.to_be_bytes());
+        assert_eq!(unpack(&bad_marker), Err(CodeError::InvalidMarker));
+    }
+}
@@ -0,0 +1,257 @@
+use std::{fmt::Write, fs, io, path::Path};
+#[cfg(feature = "image")]
+use image::{ImageResult, Rgb, RgbImage};
+use crate::algo;
+
+const WALL: [u8; 3] = [34, 34, 34];
+const FLOOR: [u8; 3] = [255, 255, 255];
+const START: [u8; 3] = [0, 0, 255];
+const END: [u8; 3] = [0, 255, 0];
+const SOLUTION: [u8; 3] = [220, 40, 40];
+
+#[derive(Debug, Clone, Copy)]
+pub struct ExportOptions {
+    // Side length of one tile, in pixels
+    pub cell_size: u32,
+    // Overlay the shortest S -> E route
+    pub solution: bool,
+    // Colour the start and end tiles
+    pub markers: bool,
+}
+
+impl Default for ExportOptions {
+    fn default() -> Self {
+        ExportOptions {
+            cell_size: 16,
+            solution: false,
+            markers: true,
+        }
+    }
+}
+
+pub fn to_svg(maz: &[Vec<char>], options: &ExportOptions) -> String {
+    let size = options.cell_size.max(1);
+    let (w, h) = dimensions(maz, size);
+    let mut svg = String::new();
+
+    let _ = writeln!(svg, r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}" shape-rendering="crispEdges">"#);
+    let _ = writeln!(svg, r#"<rect width="{w}" height="{h}" fill="{}"/>"#, hex(FLOOR));
+
+    for (r, line) in maz.iter().enumerate() {
+        for (c, &tile) in line.iter().enumerate() {
+            let fill = match tile_color(tile, options) {
+                Some(fill) => fill,
+                None => continue,
+            };
+            let _ = writeln!(
+                svg,
+                r#"<rect x="{}" y="{}" width="{size}" height="{size}" fill="{}"/>"#,
+                c as u32 * size,
+                r as u32 * size,
+                hex(fill),
+            );
+        }
+    }
+
+    if options.solution {
+        if let Some(path) = algo::solve(maz) {
+            let points: Vec<String> = path
+                .iter()
+                .map(|&(r, c)| {
+                    let half = size as f32 / 2.0;
+                    format!("{},{}", c as f32 * size as f32 + half, r as f32 * size as f32 + half)
+                })
+                .collect();
+            let _ = writeln!(
+                svg,
+                r#"<polyline points="{}" fill="none" stroke="{}" stroke-width="{}" stroke-linecap="round" stroke-linejoin="round"/>"#,
+                points.join(" "),
+                hex(SOLUTION),
+                (size as f32 / 3.0).max(1.0),
+            );
+        }
+    }
+
+    svg.push_str("</svg>\n");
+    svg
+}
+
+#[cfg(feature = "image")]
+pub fn to_image(maz: &[Vec<char>], options: &ExportOptions) -> RgbImage {
+    let size = options.cell_size.max(1);
+    let (w, h) = dimensions(maz, size);
+    let mut img = RgbImage::from_pixel(w, h, Rgb(FLOOR));
+
+    let mut fill_cell = |r: usize, c: usize, inset: u32, color: [u8; 3]| {
+        for y in inset..size - inset {
+            for x in inset..size - inset {
+                img.put_pixel(c as u32 * size + x, r as u32 * size + y, Rgb(color));
+            }
+        }
+    };
+
+    for (r, line) in maz.iter().enumerate() {
+        for (c, &tile) in line.iter().enumerate() {
+            if let Some(color) = tile_color(tile, options) {
+                fill_cell(r, c, 0, color);
+            }
+        }
+    }
+
+    if options.solution {
+        if let Some(path) = algo::solve(maz) {
+            // Skip the marker tiles so S and E stay visible under the route
+            let inset = size / 3;
+            for &(r, c) in path.iter().skip(1).take(path.len().saturating_sub(2)) {
+                fill_cell(r, c, inset, SOLUTION);
+            }
+        }
+    }
+
+    img
+}
+
+// Raw RGBA8 pixels, row by row, for showing a maze as a texture without the image crate
+pub fn to_rgba(maz: &[Vec<char>], options: &ExportOptions) -> (u32, u32, Vec<u8>) {
+    let size = options.cell_size.max(1);
+    let (w, h) = dimensions(maz, size);
+    let route: Vec<(usize, usize)> = match options.solution {
+        true => algo::solve(maz).unwrap_or_default(),
+        false => Vec::new(),
+    };
+
+    let mut pixels = Vec::with_capacity((w * h * 4) as usize);
+    for y in 0..h {
+        for x in 0..w {
+            let (r, c) = ((y / size) as usize, (x / size) as usize);
+            let tile = maz[r].get(c).copied().unwrap_or('.');
+            let color = match tile_color(tile, options) {
+                Some(color) => color,
+                None if route.contains(&(r, c)) => SOLUTION,
+                None => FLOOR,
+            };
+            pixels.extend_from_slice(&color);
+            pixels.push(255);
+        }
+    }
+
+    (w, h, pixels)
+}
+
+pub fn save_svg<P: AsRef<Path>>(maz: &[Vec<char>], path: P, options: &ExportOptions) -> io::Result<()> {
+    fs::write(path, to_svg(maz, options))
+}
+
+#[cfg(feature = "image")]
+pub fn save_png<P: AsRef<Path>>(maz: &[Vec<char>], path: P, options: &ExportOptions) -> ImageResult<()> {
+    to_image(maz, options).save_with_format(path, image::ImageFormat::Png)
+}
+
+fn tile_color(tile: char, options: &ExportOptions) -> Option<[u8; 3]> {
+    match tile {
+        '#' => Some(WALL),
+        'S' if options.markers => Some(START),
+        'E' if options.markers => Some(END),
+        _ => None,
+    }
+}
+
+fn dimensions(maz: &[Vec<char>], size: u32) -> (u32, u32) {
+    let col = maz.iter().map(|line| line.len()).max().unwrap_or(0) as u32;
+    (col * size, maz.len() as u32 * size)
+}
+
+fn hex(color: [u8; 3]) -> String {
+    format!("#{:02x}{:02x}{:02x}", color[0], color[1], color[2])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::grid;
+
+    // The route runs S (0, 0) -> (0, 1) -> (1, 1) -> (2, 1) -> E (2, 2), (0, 2) is floor off it
+    const MAZE: &str = "S..\n#.#\n#.E";
+
+    fn options(cell_size: u32, solution: bool) -> ExportOptions {
+        ExportOptions { cell_size, solution, ..Default::default() }
+    }
+
+    // Colour at the middle of a tile
+    fn rgba_at(maz: &[Vec<char>], options: &ExportOptions, r: u32, c: u32) -> [u8; 3] {
+        let (w, _, pixels) = to_rgba(maz, options);
+        let size = options.cell_size.max(1);
+        let i = (((r * size + size / 2) * w + c * size + size / 2) * 4) as usize;
+        [pixels[i], pixels[i + 1], pixels[i + 2]]
+    }
+
+    #[test]
+    fn sizes_output_by_cell_size() {
+        let maz = grid(MAZE);
+        for size in [0, 1, 3, 16] {
+            let px = size.max(1) * 3;
+            let (w, h, pixels) = to_rgba(&maz, &options(size, false));
+            assert_eq!((w, h), (px, px));
+            assert_eq!(pixels.len(), (px * px * 4) as usize);
+
+            let svg = to_svg(&maz, &options(size, false));
+            assert!(svg.contains(&format!(r#"width="{px}" height="{px}""#)), "{}", svg);
+        }
+
+        // Ragged rows are padded out to the longest one
+        assert_eq!(to_rgba(&grid("S\n..E"), &options(2, false)).0, 6);
+    }
+
+    #[test]
+    fn colours_tiles() {
+        let maz = grid(MAZE);
+        let options = options(4, false);
+        assert_eq!(rgba_at(&maz, &options, 0, 0), START);
+        assert_eq!(rgba_at(&maz, &options, 2, 2), END);
+        assert_eq!(rgba_at(&maz, &options, 1, 0), WALL);
+        assert_eq!(rgba_at(&maz, &options, 0, 2), FLOOR);
+
+        let plain = ExportOptions { markers: false, ..options };
+        assert_eq!(rgba_at(&maz, &plain, 0, 0), FLOOR);
+        assert_eq!(rgba_at(&maz, &plain, 2, 2), FLOOR);
+
+        let svg = to_svg(&maz, &options);
+        assert_eq!(svg.matches(&format!(r#"fill="{}""#, hex(WALL))).count(), 3);
+        assert_eq!(svg.matches(&format!(r#"fill="{}""#, hex(START))).count(), 1);
+        assert_eq!(svg.matches(&format!(r#"fill="{}""#, hex(END))).count(), 1);
+    }
+
+    #[test]
+    fn draws_solution_only_when_asked() {
+        let maz = grid(MAZE);
+        assert_eq!(rgba_at(&maz, &options(4, false), 1, 1), FLOOR);
+        assert_eq!(rgba_at(&maz, &options(4, true), 1, 1), SOLUTION);
+        // Off the route, and under the markers, nothing changes
+        assert_eq!(rgba_at(&maz, &options(4, true), 0, 2), FLOOR);
+        assert_eq!(rgba_at(&maz, &options(4, true), 0, 0), START);
+
+        assert!(!to_svg(&maz, &options(4, false)).contains("<polyline"));
+        assert!(to_svg(&maz, &options(4, true)).contains(r#"<polyline points="2,2 6,2 6,6 6,10 10,10""#));
+        // No route, no overlay
+        assert!(!to_svg(&grid("S#E"), &options(4, true)).contains("<polyline"));
+    }
+
+    #[cfg(feature = "image")]
+    #[test]
+    fn renders_images() {
+        let maz = grid(MAZE);
+        let img = to_image(&maz, &options(6, false));
+        assert_eq!(img.dimensions(), (18, 18));
+        assert_eq!(img.get_pixel(3, 3).0, START);
+        assert_eq!(img.get_pixel(15, 15).0, END);
+        assert_eq!(img.get_pixel(3, 9).0, WALL);
+        assert_eq!(img.get_pixel(9, 9).0, FLOOR);
+
+        // The route is drawn inset, leaving the edges of its tiles as floor
+        let img = to_image(&maz, &options(6, true));
+        assert_eq!(img.get_pixel(9, 9).0, SOLUTION);
+        assert_eq!(img.get_pixel(6, 6).0, FLOOR);
+        assert_eq!(img.get_pixel(15, 3).0, FLOOR);
+        assert_eq!(img.get_pixel(3, 3).0, START);
+    }
+}
@@ -0,0 +1,168 @@
+use std::f32::consts::FRAC_PI_8;
+use bevy::{prelude::*, transform::TransformSystem};
+use bevy_rapier3d::prelude::PhysicsSet;
+use crate::{
+    algo,
+    camera::{GameCamera, EYE_HEIGHT},
+    input::{Action, Actions},
+    menu::{GameState, PauseState},
+    world::{Maze, StreamFocus},
+};
+
+// Tiles per second along the route, sped up on long routes so the flight stays short
+const FLY_SPEED: f32 = 5.0;
+const MAX_FLY_TIME: f32 = 25.0;
+// How far ahead along the route the camera looks, in tiles
+const LOOK_AHEAD: f32 = 1.5;
+// Seconds spent rising from the exit to the overview, then holding it
+const RISE_TIME: f32 = 3.0;
+const HOLD_TIME: f32 = 2.0;
+
+pub struct FlythroughPlugin;
+
+impl Plugin for FlythroughPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(PauseState::Flythrough), start_flythrough)
+            .add_systems(OnExit(PauseState::Flythrough), end_flythrough)
+            // After the camera systems have placed the camera for the frame, so this has the last word
+            .add_systems(PostUpdate, fly
+                .after(PhysicsSet::Writeback)
+                .before(TransformSystem::TransformPropagate)
+                .run_if(in_state(PauseState::Flythrough)));
+    }
+}
+
+// Where the game goes once the flythrough is over or skipped
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AfterFlythrough {
+    Won,
+    Paused,
+}
+
+// Flies the camera along the shortest S -> E route at head height, then rises above the maze
+#[derive(Resource)]
+struct Flythrough {
+    route: CubicCurve<Vec3>,
+    // Tiles from S to E, 0.0 when there is no route to fly
+    length: f32,
+    speed: f32,
+    overview: Transform,
+    // Real time since the start, the game clock is stopped meanwhile
+    elapsed: f32,
+    // Where the camera was before, to hand it back unchanged
+    restore: Transform,
+}
+
+impl Flythrough {
+    fn fly_time(&self) -> f32 {
+        self.length / self.speed
+    }
+
+    // The camera `t` tiles along the route, looking a little further down it
+    fn along_route(&self, t: f32) -> Transform {
+        if self.length <= 0.0 {
+            return self.restore;
+        }
+
+        let position = self.route.position(t);
+        let ahead = self.route.position((t + LOOK_AHEAD).min(self.length)) - position;
+        let direction = match ahead.length_squared() > 0.01 {
+            true => ahead,
+            false => self.route.velocity(t),
+        };
+        Transform::from_translation(position).looking_to(direction, Vec3::Y)
+    }
+
+    fn camera_at(&self, elapsed: f32) -> Transform {
+        let fly_time = self.fly_time();
+        let exit = self.along_route(self.length);
+        if elapsed < fly_time {
+            return self.along_route(elapsed * self.speed);
+        }
+
+        let k = ((elapsed - fly_time) / RISE_TIME).min(1.0);
+        let k = k * k * (3.0 - 2.0 * k);
+        Transform {
+            translation: exit.translation.lerp(self.overview.translation, k),
+            rotation: exit.rotation.slerp(self.overview.rotation, k),
+            ..exit
+        }
+    }
+}
+
+fn start_flythrough(
+    mut commands: Commands,
+    maze: Res<Maze>,
+    cam_q: Query<(Entity, &Transform), With<GameCamera>>,
+) {
+    let Ok((camera, transform)) = cam_q.get_single() else {
+        return;
+    };
+
+    let points: Vec<Vec3> = algo::solve(&maze.0)
+        .unwrap_or_default()
+        .iter()
+        .map(|&(r, c)| Vec3::new(c as f32, EYE_HEIGHT, r as f32))
+        .collect();
+    let length = points.len().saturating_sub(1) as f32;
+
+    // High enough for the camera's 45 degree field of view to take in the whole maze
+    let m = maze.0.len() as f32;
+    let n = maze.0.iter().map(|line| line.len()).max().unwrap_or(0) as f32;
+    let center = Vec3::new(n/2.0 - 0.5, 0.0, m/2.0 - 0.5);
+    let height = n.max(m) / 2.0 / FRAC_PI_8.tan() + 2.0;
+
+    commands.insert_resource(Flythrough {
+        route: CubicCardinalSpline::new_catmull_rom(points).to_curve(),
+        length,
+        speed: FLY_SPEED.max(length / MAX_FLY_TIME),
+        overview: Transform::from_translation(center + Vec3::Y * height).looking_at(center, Vec3::NEG_Z),
+        elapsed: 0.0,
+        restore: *transform,
+    });
+    // Walls have to be there wherever the camera flies, not just around the player
+    commands.entity(camera).insert(StreamFocus);
+}
+
+fn fly(
+    time: Res<Time<Real>>,
+    actions: Actions,
+    after: Option<Res<AfterFlythrough>>,
+    flight: Option<ResMut<Flythrough>>,
+    mut cam_q: Query<&mut Transform, With<GameCamera>>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut next_pause_state: ResMut<NextState<PauseState>>,
+) {
+    let Some(mut flight) = flight else {
+        return;
+    };
+    flight.elapsed += time.delta_seconds();
+
+    let skipped = [Action::Confirm, Action::Back, Action::Pause].into_iter().any(|action| actions.just_pressed(action));
+    if skipped || flight.elapsed > flight.fly_time() + RISE_TIME + HOLD_TIME {
+        match after.as_deref() {
+            Some(AfterFlythrough::Won) => next_state.set(GameState::Won),
+            _ => next_pause_state.set(PauseState::Paused),
+        }
+        return;
+    }
+
+    for mut transform in cam_q.iter_mut() {
+        *transform = flight.camera_at(flight.elapsed);
+    }
+}
+
+fn end_flythrough(
+    mut commands: Commands,
+    flight: Option<Res<Flythrough>>,
+    mut cam_q: Query<(Entity, &mut Transform), With<GameCamera>>,
+) {
+    for (camera, mut transform) in cam_q.iter_mut() {
+        if let Some(flight) = flight.as_deref() {
+            *transform = flight.restore;
+        }
+        commands.entity(camera).remove::<StreamFocus>();
+    }
+    commands.remove_resource::<Flythrough>();
+    commands.remove_resource::<AfterFlythrough>();
+}
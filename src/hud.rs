@@ -0,0 +1,104 @@
+use bevy::prelude::*;
+use crate::{
+    algo,
+    menu::{format_time, GameState},
+    player::{Player, RunStats},
+    world::{LevelEntity, Maze, MazeLoaded, MazeOrigin},
+};
+
+pub struct HudPlugin;
+
+impl Plugin for HudPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ExitDistances>()
+            .add_systems(OnEnter(GameState::InGame), setup_hud.after(MazeLoaded))
+            .add_systems(Update, update_hud.run_if(in_state(GameState::InGame)));
+    }
+}
+
+#[derive(Component)]
+struct HudText;
+
+// Steps from every tile to the exit, used to measure how much of the walk so
+// far actually brought the player closer
+#[derive(Resource, Default)]
+struct ExitDistances(Vec<Vec<Option<usize>>>);
+
+fn setup_hud(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    maze: Res<Maze>,
+    origin: Res<MazeOrigin>,
+    mut exit_distances: ResMut<ExitDistances>,
+) {
+    exit_distances.0 = match algo::find_tile(&maze.0, 'E') {
+        Some(end) => algo::distances_from(&maze.0, end),
+        None => Vec::new(),
+    };
+
+    let source = match origin.0 {
+        Some((algorithm, seed)) => format!("{} maze, seed {}", algorithm, seed),
+        None => "custom maze".to_string(),
+    };
+    let style = TextStyle {
+        font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+        font_size: 24.0,
+        color: Color::WHITE,
+    };
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(10.0),
+                    left: Val::Px(10.0),
+                    padding: UiRect::all(Val::Px(10.0)),
+                    flex_direction: FlexDirection::Column,
+                    ..default()
+                },
+                background_color: Color::srgba(0.1, 0.1, 0.1, 0.6).into(),
+                ..default()
+            },
+            Name::new("HUD"),
+            LevelEntity,
+        ))
+        .with_children(|parent| {
+            parent.spawn((TextBundle::from_section("", style.clone()), HudText));
+            parent.spawn(TextBundle::from_section(source, style));
+        });
+}
+
+fn update_hud(
+    stats: Res<RunStats>,
+    exit_distances: Res<ExitDistances>,
+    player_q: Query<&Transform, With<Player>>,
+    mut text_q: Query<&mut Text, With<HudText>>,
+) {
+    let Ok(mut text) = text_q.get_single_mut() else {
+        return;
+    };
+
+    let mut hud = format!(
+        "Time: {}\nDistance: {:.1} tiles",
+        format_time(stats.elapsed),
+        stats.distance
+    );
+
+    // Progress is how much closer to the exit the player is than at the start
+    let remaining = player_q.get_single().ok().and_then(|player| {
+        let r = player.translation.z.round().max(0.0) as usize;
+        let c = player.translation.x.round().max(0.0) as usize;
+        exit_distances.0.get(r).and_then(|line| line.get(c)).copied().flatten()
+    });
+    if let (Some(optimal), Some(remaining)) = (stats.optimal, remaining) {
+        let progress = optimal.saturating_sub(remaining);
+        hud += &format!(
+            "\nOptimal: {} tiles\nEfficiency: {:.0}%",
+            optimal,
+            100.0 * stats.efficiency(progress)
+        );
+    }
+
+    text.sections[0].value = hud;
+}
@@ -0,0 +1,192 @@
+use std::path::Path;
+use image::{ImageResult, Rgb, RgbImage};
+
+/// Converts a drawing of a maze into the tile grid used by `map.txt`.
+/// Dark pixels become walls, light pixels floor, blue pixels the start and
+/// green pixels the end. When `cell_size` is `None` it is detected from the
+/// widths of the wall and floor runs in the image.
+pub fn from_image<P: AsRef<Path>>(path: P, cell_size: Option<f32>) -> ImageResult<Vec<Vec<char>>> {
+    let img = image::open(path)?.into_rgb8();
+    Ok(from_rgb(&img, cell_size))
+}
+
+pub fn from_rgb(img: &RgbImage, cell_size: Option<f32>) -> Vec<Vec<char>> {
+    let (w, h) = img.dimensions();
+    if w == 0 || h == 0 {
+        return Vec::new();
+    }
+
+    let tiles: Vec<Vec<char>> = (0..h)
+        .map(|y| (0..w).map(|x| classify(img.get_pixel(x, y))).collect())
+        .collect();
+
+    let size = cell_size
+        .filter(|s| *s >= 1.0)
+        .unwrap_or_else(|| detect_cell_size(&tiles));
+
+    let row = ((h as f32 / size).round() as usize).max(1);
+    let col = ((w as f32 / size).round() as usize).max(1);
+
+    let mut maz = vec![vec!['.'; col]; row];
+    for (r, line) in maz.iter_mut().enumerate() {
+        for (c, tile) in line.iter_mut().enumerate() {
+            *tile = sample_cell(&tiles, r, c, size);
+        }
+    }
+
+    // A marker drawn larger than one cell would otherwise leave several
+    // start or end tiles behind
+    for marker in ['S', 'E'] {
+        let mut seen = false;
+        for tile in maz.iter_mut().flatten().filter(|t| **t == marker) {
+            if seen {
+                *tile = '.';
+            }
+            seen = true;
+        }
+    }
+
+    maz
+}
+
+fn classify(px: &Rgb<u8>) -> char {
+    let [r, g, b] = px.0;
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+
+    if max - min > 64 {
+        // Saturated pixels are markers, anything else coloured counts as floor
+        if b >= r && b >= g {
+            'S'
+        } else if g >= r && g >= b {
+            'E'
+        } else {
+            '.'
+        }
+    } else if (r as u32 + g as u32 + b as u32) / 3 < 128 {
+        '#'
+    } else {
+        '.'
+    }
+}
+
+fn detect_cell_size(tiles: &[Vec<char>]) -> f32 {
+    let w = tiles[0].len();
+    let mut runs = Vec::new();
+
+    let mut push_runs = |line: &mut dyn Iterator<Item = bool>| {
+        let mut prev = None;
+        let mut len = 0;
+        for wall in line {
+            if Some(wall) == prev {
+                len += 1;
+            } else {
+                if len > 0 {
+                    runs.push(len);
+                }
+                prev = Some(wall);
+                len = 1;
+            }
+        }
+        runs.push(len);
+    };
+
+    for line in tiles {
+        push_runs(&mut line.iter().map(|&t| t == '#'));
+    }
+    for x in 0..w {
+        push_runs(&mut tiles.iter().map(|line| line[x] == '#'));
+    }
+
+    // Anti-aliasing and compression leave a few very short runs along
+    // edges, so the smallest length has to show up often enough to count
+    let mut counts = std::collections::BTreeMap::new();
+    for &len in &runs {
+        *counts.entry(len).or_insert(0usize) += 1;
+    }
+    let threshold = (runs.len() / 50).max(1);
+    let min = counts
+        .iter()
+        .find(|(_, &n)| n >= threshold)
+        .map_or(1, |(&len, _)| len);
+
+    // Every run should be close to a whole number of cells; averaging over
+    // all of them smooths out rounding in scaled drawings
+    let (mut total, mut cells) = (0.0, 0.0);
+    for &len in &runs {
+        let k = (len as f32 / min as f32).round();
+        if k >= 1.0 {
+            total += len as f32;
+            cells += k;
+        }
+    }
+
+    if cells > 0.0 { total / cells } else { 1.0 }
+}
+
+// Majority vote over the middle of a cell, ignoring its edges where
+// neighbouring tiles bleed in
+fn sample_cell(tiles: &[Vec<char>], r: usize, c: usize, size: f32) -> char {
+    let h = tiles.len();
+    let w = tiles[0].len();
+    let mut votes = [('#', 0), ('.', 0), ('S', 0), ('E', 0)];
+
+    for i in 1..4 {
+        for j in 1..4 {
+            let y = ((r as f32 + i as f32 / 4.0) * size) as usize;
+            let x = ((c as f32 + j as f32 / 4.0) * size) as usize;
+            let tile = tiles[y.min(h - 1)][x.min(w - 1)];
+            if let Some(vote) = votes.iter_mut().find(|(t, _)| *t == tile) {
+                vote.1 += 1;
+            }
+        }
+    }
+
+    votes.iter().max_by_key(|(_, n)| *n).map_or('.', |(t, _)| *t)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{algo::Algorithm, testing::{generated, grid}};
+
+    // Draws each tile as a `size` pixel square, the way a paint program export would
+    fn draw(maz: &[Vec<char>], size: u32) -> RgbImage {
+        let (w, h) = (maz[0].len() as u32 * size, maz.len() as u32 * size);
+        RgbImage::from_fn(w, h, |x, y| match maz[(y / size) as usize][(x / size) as usize] {
+            '#' => Rgb([20, 20, 20]),
+            'S' => Rgb([30, 60, 220]),
+            'E' => Rgb([40, 200, 60]),
+            _ => Rgb([240, 240, 240]),
+        })
+    }
+
+    #[test]
+    fn detects_cell_size() {
+        for g in generated(&[(15, 21)], 0..2) {
+            for size in [1, 4, 9, 16] {
+                assert_eq!(from_rgb(&draw(&g.maz, size), None), g.maz, "{} at {}px", g.label(), size);
+            }
+        }
+    }
+
+    #[test]
+    fn uses_given_cell_size() {
+        let maz = Algorithm::Prim.generate(11, 11, 7);
+        assert_eq!(from_rgb(&draw(&maz, 6), Some(6.0)), maz);
+    }
+
+    #[test]
+    fn keeps_one_tile_per_marker() {
+        // Start and end drawn two cells wide
+        let img = draw(&grid("#####\nSS..#\n#..EE\n#####"), 5);
+        let maz = from_rgb(&img, Some(5.0));
+        assert_eq!(maz.iter().flatten().filter(|&&t| t == 'S').count(), 1);
+        assert_eq!(maz.iter().flatten().filter(|&&t| t == 'E').count(), 1);
+    }
+
+    #[test]
+    fn empty_image_has_no_tiles() {
+        assert!(from_rgb(&RgbImage::new(0, 0), None).is_empty());
+    }
+}
@@ -0,0 +1,263 @@
+use std::{collections::BTreeMap, error::Error, fs, path::Path};
+use bevy::{ecs::system::SystemParam, prelude::*};
+use serde::{Deserialize, Serialize};
+use crate::settings;
+
+// Stick travel ignored around the centre, so worn sticks don't creep
+pub const STICK_DEADZONE: f32 = 0.15;
+
+pub struct ActionsPlugin;
+
+impl Plugin for ActionsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Bindings::load());
+    }
+}
+
+// Everything the player can do, so keys can be rebound without touching the systems that read them
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Action {
+    MoveForward,
+    MoveBack,
+    MoveLeft,
+    MoveRight,
+    Sprint,
+    Interact,
+    ToggleMap,
+    ToggleCamera,
+    Pause,
+    MenuUp,
+    MenuDown,
+    MenuLeft,
+    MenuRight,
+    Confirm,
+    Back,
+}
+
+impl Action {
+    pub const ALL: [Action; 15] = [
+        Action::MoveForward,
+        Action::MoveBack,
+        Action::MoveLeft,
+        Action::MoveRight,
+        Action::Sprint,
+        Action::Interact,
+        Action::ToggleMap,
+        Action::ToggleCamera,
+        Action::Pause,
+        Action::MenuUp,
+        Action::MenuDown,
+        Action::MenuLeft,
+        Action::MenuRight,
+        Action::Confirm,
+        Action::Back,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Action::MoveForward => "Forward",
+            Action::MoveBack => "Back",
+            Action::MoveLeft => "Left",
+            Action::MoveRight => "Right",
+            Action::Sprint => "Sprint",
+            Action::Interact => "Interact",
+            Action::ToggleMap => "Map",
+            Action::ToggleCamera => "Camera",
+            Action::Pause => "Pause",
+            Action::MenuUp => "Menu up",
+            Action::MenuDown => "Menu down",
+            Action::MenuLeft => "Menu left",
+            Action::MenuRight => "Menu right",
+            Action::Confirm => "Confirm",
+            Action::Back => "Menu back",
+        }
+    }
+}
+
+// Which half of an action's bindings a control refers to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BindSlot {
+    Key,
+    Gamepad,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ActionBindings {
+    pub keys: Vec<KeyCode>,
+    pub gamepad: Vec<GamepadButtonType>,
+}
+
+// Keys and gamepad buttons for every action, saved to bindings.toml next to the settings
+#[derive(Resource, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Bindings(pub BTreeMap<Action, ActionBindings>);
+
+impl Default for Bindings {
+    fn default() -> Self {
+        use GamepadButtonType as Pad;
+
+        let bind = |keys: &[KeyCode], gamepad: &[Pad]| ActionBindings {
+            keys: keys.to_vec(),
+            gamepad: gamepad.to_vec(),
+        };
+        Bindings(BTreeMap::from([
+            (Action::MoveForward, bind(&[KeyCode::KeyW], &[])),
+            (Action::MoveBack, bind(&[KeyCode::KeyS], &[])),
+            (Action::MoveLeft, bind(&[KeyCode::KeyA], &[])),
+            (Action::MoveRight, bind(&[KeyCode::KeyD], &[])),
+            (Action::Sprint, bind(&[KeyCode::ShiftLeft], &[Pad::LeftThumb])),
+            (Action::Interact, bind(&[KeyCode::KeyE], &[Pad::South])),
+            (Action::ToggleMap, bind(&[KeyCode::KeyM], &[Pad::Select])),
+            (Action::ToggleCamera, bind(&[KeyCode::KeyV], &[Pad::North])),
+            (Action::Pause, bind(&[KeyCode::Escape], &[Pad::Start])),
+            (Action::MenuUp, bind(&[KeyCode::ArrowUp, KeyCode::KeyW], &[Pad::DPadUp])),
+            (Action::MenuDown, bind(&[KeyCode::ArrowDown, KeyCode::KeyS], &[Pad::DPadDown])),
+            (Action::MenuLeft, bind(&[KeyCode::ArrowLeft, KeyCode::KeyA], &[Pad::DPadLeft])),
+            (Action::MenuRight, bind(&[KeyCode::ArrowRight, KeyCode::KeyD], &[Pad::DPadRight])),
+            (Action::Confirm, bind(&[KeyCode::Enter, KeyCode::NumpadEnter, KeyCode::Space], &[Pad::South])),
+            (Action::Back, bind(&[KeyCode::Escape], &[Pad::East])),
+        ]))
+    }
+}
+
+impl Bindings {
+    pub fn get(&self, action: Action) -> Option<&ActionBindings> {
+        self.0.get(&action)
+    }
+
+    // Makes `key` the action's main key, keeping any other keys bound to it
+    pub fn rebind_key(&mut self, action: Action, key: KeyCode) {
+        replace_first(&mut self.0.entry(action).or_default().keys, key);
+    }
+
+    pub fn rebind_gamepad(&mut self, action: Action, button: GamepadButtonType) {
+        replace_first(&mut self.0.entry(action).or_default().gamepad, button);
+    }
+
+    // Name of the main key or gamepad button, for the controls menu
+    pub fn describe(&self, action: Action, slot: BindSlot) -> String {
+        let bound = self.get(action);
+        let name = match slot {
+            BindSlot::Key => bound.and_then(|b| b.keys.first()).map(|key| {
+                let name = format!("{:?}", key);
+                name.strip_prefix("Key").or(name.strip_prefix("Digit")).unwrap_or(&name).to_string()
+            }),
+            BindSlot::Gamepad => bound.and_then(|b| b.gamepad.first()).map(|button| format!("{:?}", button)),
+        };
+        name.unwrap_or_else(|| "-".to_string())
+    }
+
+    // Falls back to the defaults when there is no bindings file yet or it can't be read.
+    // Actions missing from an older file keep their default bindings.
+    pub fn load() -> Bindings {
+        let mut bindings = Bindings::default();
+        let Some(path) = settings::config_file("bindings.toml") else {
+            return bindings;
+        };
+        if !path.exists() {
+            return bindings;
+        }
+
+        match Self::read(&path) {
+            Ok(saved) => bindings.0.extend(saved.0),
+            Err(e) => println!("Could not read {}: {}", path.display(), e),
+        }
+        bindings
+    }
+
+    fn read(path: &Path) -> Result<Bindings, Box<dyn Error>> {
+        Ok(toml::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    pub fn save(&self) -> Result<(), Box<dyn Error>> {
+        let path = settings::config_file("bindings.toml").ok_or("no config directory")?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, toml::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+fn replace_first<T: PartialEq>(list: &mut Vec<T>, new: T) {
+    if list.first() == Some(&new) {
+        return;
+    }
+    if !list.is_empty() {
+        list.remove(0);
+    }
+    list.retain(|old| *old != new);
+    list.insert(0, new);
+}
+
+// Reads actions through the current bindings, keyboard and gamepad alike
+#[derive(SystemParam)]
+pub struct Actions<'w> {
+    bindings: Res<'w, Bindings>,
+    keys: Res<'w, ButtonInput<KeyCode>>,
+    gamepad: GamepadInput<'w>,
+}
+
+impl Actions<'_> {
+    pub fn pressed(&self, action: Action) -> bool {
+        self.bindings.get(action).is_some_and(|bound| {
+            self.keys.any_pressed(bound.keys.iter().copied())
+                || bound.gamepad.iter().any(|&button| self.gamepad.pressed(button))
+        })
+    }
+
+    pub fn just_pressed(&self, action: Action) -> bool {
+        self.bindings.get(action).is_some_and(|bound| {
+            self.keys.any_just_pressed(bound.keys.iter().copied())
+                || bound.gamepad.iter().any(|&button| self.gamepad.just_pressed(button))
+        })
+    }
+
+    pub fn left_stick(&self) -> Vec2 {
+        self.gamepad.left_stick()
+    }
+}
+
+// Every connected gamepad counts, so it doesn't matter which one the player picked up
+#[derive(SystemParam)]
+pub struct GamepadInput<'w> {
+    gamepads: Res<'w, Gamepads>,
+    axes: Res<'w, Axis<GamepadAxis>>,
+    buttons: Res<'w, ButtonInput<GamepadButton>>,
+}
+
+impl GamepadInput<'_> {
+    pub fn pressed(&self, button_type: GamepadButtonType) -> bool {
+        self.gamepads
+            .iter()
+            .any(|pad| self.buttons.pressed(GamepadButton::new(pad, button_type)))
+    }
+
+    pub fn just_pressed(&self, button_type: GamepadButtonType) -> bool {
+        self.gamepads
+            .iter()
+            .any(|pad| self.buttons.just_pressed(GamepadButton::new(pad, button_type)))
+    }
+
+    // The most deflected left stick, x right and y up
+    pub fn left_stick(&self) -> Vec2 {
+        self.stick(GamepadAxisType::LeftStickX, GamepadAxisType::LeftStickY)
+    }
+
+    pub fn right_stick(&self) -> Vec2 {
+        self.stick(GamepadAxisType::RightStickX, GamepadAxisType::RightStickY)
+    }
+
+    fn stick(&self, x: GamepadAxisType, y: GamepadAxisType) -> Vec2 {
+        self.gamepads
+            .iter()
+            .map(|pad| {
+                Vec2::new(
+                    self.axes.get(GamepadAxis::new(pad, x)).unwrap_or(0.0),
+                    self.axes.get(GamepadAxis::new(pad, y)).unwrap_or(0.0),
+                )
+            })
+            .max_by(|a, b| a.length_squared().total_cmp(&b.length_squared()))
+            .unwrap_or(Vec2::ZERO)
+    }
+}
@@ -0,0 +1,50 @@
+//! Maze generation, solving and import/export, plus the Bevy plugins that make up
+//! the labyrinth game so other tools and games can embed it.
+
+pub mod algo;
+#[cfg(feature = "game")]
+pub mod camera;
+pub mod code;
+pub mod export;
+#[cfg(feature = "game")]
+pub mod flythrough;
+#[cfg(feature = "game")]
+pub mod hud;
+#[cfg(feature = "image")]
+pub mod import;
+#[cfg(feature = "game")]
+pub mod input;
+pub mod map;
+#[cfg(feature = "game")]
+pub mod menu;
+#[cfg(feature = "game")]
+pub mod minimap;
+#[cfg(feature = "game")]
+pub mod player;
+#[cfg(feature = "game")]
+pub mod settings;
+pub mod tmx;
+#[cfg(feature = "game")]
+pub mod world;
+
+#[cfg(test)]
+mod testing;
+
+#[cfg(feature = "game")]
+pub use camera::CameraPlugin;
+#[cfg(feature = "game")]
+pub use flythrough::FlythroughPlugin;
+#[cfg(feature = "game")]
+pub use hud::HudPlugin;
+#[cfg(feature = "game")]
+pub use input::ActionsPlugin;
+#[cfg(feature = "game")]
+pub use menu::MenuPlugin;
+#[cfg(feature = "game")]
+pub use minimap::MinimapPlugin;
+#[cfg(feature = "game")]
+pub use player::PlayerPlugin;
+#[cfg(feature = "game")]
+pub use settings::SettingsPlugin;
+#[cfg(feature = "game")]
+pub use world::WorldPlugin;
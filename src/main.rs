@@ -1,22 +1,40 @@
 use bevy::prelude::*;
 use bevy_third_person_camera::*;
+#[cfg(feature = "inspector")]
 use bevy_inspector_egui::quick::WorldInspectorPlugin;
 use bevy_rapier3d::prelude::*;
-use menu::UiCameraExists;
+use labyrinth::{code, map, menu::UiCameraExists, ActionsPlugin, CameraPlugin, FlythroughPlugin, HudPlugin, MenuPlugin, MinimapPlugin, PlayerPlugin, SettingsPlugin, WorldPlugin};
 
-mod player;
-mod camera;
-mod world;
-mod menu;
-mod algo;
-
-use menu::MenuPlugin;
-use player::PlayerPlugin;
-use camera::CameraPlugin;
-use world::WorldPlugin;
-// use algo::Prim;
 fn main() {
-    App::new()
+    // `labyrinth --code <maze code>` replaces the current map with a shared maze
+    let args: Vec<String> = std::env::args().collect();
+    if let Some(i) = args.iter().position(|a| a == "--code") {
+        match args.get(i + 1).map(|c| code::from_code(c)) {
+            Some(Ok(maz)) => {
+                if let Err(e) = map::save(&maz, "assets/map.txt") {
+                    println!("{}", e);
+                }
+            }
+            Some(Err(e)) => println!("{}", e),
+            None => println!("--code expects a maze code"),
+        }
+    }
+
+    // `labyrinth --map <file>` imports a Tiled map or a maze drawn in any paint program
+    if let Some(i) = args.iter().position(|a| a == "--map") {
+        match args.get(i + 1).map(map::load) {
+            Some(Ok(maz)) => {
+                if let Err(e) = map::save(&maz, "assets/map.txt") {
+                    println!("{}", e);
+                }
+            }
+            Some(Err(e)) => println!("{}", e),
+            None => println!("--map expects a .tmx or image file"),
+        }
+    }
+
+    let mut app = App::new();
+    app
         .add_plugins((
             DefaultPlugins.set(AssetPlugin {
                 watch_for_changes_override: Some(true),
@@ -26,17 +44,18 @@ fn main() {
             CameraPlugin, 
             WorldPlugin, 
             MenuPlugin, 
+            HudPlugin, 
+            MinimapPlugin, 
+            FlythroughPlugin, 
+            SettingsPlugin, 
+            ActionsPlugin, 
             ThirdPersonCameraPlugin, 
-            WorldInspectorPlugin::new(), 
             RapierPhysicsPlugin::<NoUserData>::default()
         ))
-        .insert_resource(UiCameraExists(false))
-        .run();
+        .insert_resource(UiCameraExists(false));
 
-    
-    // let maz = Prim::new(50, 50);
+    #[cfg(feature = "inspector")]
+    app.add_plugins(WorldInspectorPlugin::new());
 
-    // for row in maz {
-    //     println!("{}", row.iter().collect::<String>());
-    // }
-}
\ No newline at end of file
+    app.run();
+}
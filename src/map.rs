@@ -0,0 +1,35 @@
+use std::{error::Error, fs::{self, File}, io::{Result, Write}, path::Path};
+use crate::tmx;
+
+/// Reads a maze from a `map.txt` style tilemap, a Tiled `.tmx` map or an image.
+pub fn load<P: AsRef<Path>>(path: P) -> std::result::Result<Vec<Vec<char>>, Box<dyn Error>> {
+    let path = path.as_ref();
+    let ext = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_ascii_lowercase());
+
+    match ext.as_deref() {
+        Some("tmx") => Ok(tmx::from_tmx(path)?),
+        #[cfg(feature = "image")]
+        Some("png" | "jpg" | "jpeg") => Ok(crate::import::from_image(path, None)?),
+        #[cfg(not(feature = "image"))]
+        Some("png" | "jpg" | "jpeg") => Err("image import needs the `image` feature".into()),
+        _ => Ok(fs::read_to_string(path)?
+            .lines()
+            .map(|line| line.chars().collect())
+            .collect()),
+    }
+}
+
+/// Writes a maze as a tilemap text file, one row per line.
+pub fn save<P: AsRef<Path>>(maz: &[Vec<char>], path: P) -> Result<()> {
+    let mut file = File::create(path)?;
+
+    for row in maz {
+        let line = row.iter().collect::<String>() + "\n";
+        file.write_all(line.as_bytes())?;
+    }
+
+    Ok(())
+}
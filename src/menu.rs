@@ -1,7 +1,26 @@
-use std::fs::File;
-use bevy::prelude::*;
-use std::io::{Write, Result};
-use crate::algo;
+use bevy::{
+    input::{keyboard::{Key, KeyboardInput}, ButtonState},
+    prelude::*,
+    render::{
+        render_asset::RenderAssetUsages,
+        render_resource::{Extent3d, TextureDimension, TextureFormat},
+        texture::ImageSampler,
+    },
+    ui::UiSystem,
+};
+use bevy_rapier3d::prelude::*;
+use bevy_third_person_camera::ThirdPersonCamera;
+use crate::{
+    algo::{self, Algorithm},
+    camera::CameraMode,
+    code, export,
+    flythrough::AfterFlythrough,
+    input::{Action, Actions, BindSlot, Bindings},
+    map,
+    player::RunStats,
+    settings::GameSettings,
+    world::{Explored, Maze, MazeOrigin},
+};
 
 // States
 #[derive(States, Debug, Clone, Eq, PartialEq, Hash, Default)]
@@ -9,7 +28,38 @@ pub enum GameState {
     #[default]
     MainMenu,
     GenerateMenu,
+    Settings,
+    Controls,
+    // Passes straight through to InGame, so restarting re-runs the level setup
+    Loading,
     InGame,
+    Won,
+}
+
+#[derive(SubStates, Debug, Clone, Eq, PartialEq, Hash, Default)]
+#[source(GameState = GameState::InGame)]
+pub enum PauseState {
+    #[default]
+    Running,
+    Paused,
+    Settings,
+    Controls,
+    Flythrough,
+}
+
+// Game time and physics stay stopped on every screen opened from the pause menu
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub struct GamePaused;
+
+impl ComputedStates for GamePaused {
+    type SourceStates = PauseState;
+
+    fn compute(sources: PauseState) -> Option<Self> {
+        match sources {
+            PauseState::Running => None,
+            PauseState::Paused | PauseState::Settings | PauseState::Controls | PauseState::Flythrough => Some(GamePaused),
+        }
+    }
 }
 
 // Plugin
@@ -18,19 +68,63 @@ pub struct MenuPlugin;
 impl Plugin for MenuPlugin {
     fn build(&self, app: &mut App) {
         app.init_state::<GameState>()
+            .add_sub_state::<PauseState>()
+            .add_computed_state::<GamePaused>()
             .insert_resource(UiCameraExists(false))
+            .init_resource::<GenSettings>()
+            .init_resource::<Rebinding>()
             .add_systems(Startup, setup_ui_camera)
+            // After the UI has read the mouse, so keyboard and gamepad presses reach the same handlers
+            .add_systems(
+                PreUpdate,
+                (
+                    release_nav_press,
+                    menu_navigation
+                        .run_if(in_menu)
+                        .run_if(|rebinding: Res<Rebinding>| rebinding.0.is_none()),
+                    focus_on_hover,
+                    capture_binding,
+                )
+                    .chain()
+                    .after(UiSystem::Focus),
+            )
             .add_systems(OnEnter(GameState::MainMenu), setup_menu)
             .add_systems(OnEnter(GameState::GenerateMenu), (setup_gen_menu, setup_ui_camera))
+            .add_systems(OnEnter(GameState::Won), setup_win_menu)
+            .add_systems(OnEnter(GameState::Loading), start_game)
+            .add_systems(OnEnter(GameState::Settings), setup_settings_menu)
+            .add_systems(OnEnter(GamePaused), pause_game)
+            .add_systems(OnExit(GamePaused), resume_game)
+            .add_systems(OnEnter(PauseState::Paused), setup_pause_menu)
+            .add_systems(OnExit(PauseState::Paused), cleanup_menu)
+            .add_systems(OnEnter(PauseState::Settings), setup_settings_menu)
+            .add_systems(OnExit(PauseState::Settings), (cleanup_menu, save_settings))
+            .add_systems(OnEnter(GameState::Controls), setup_controls_menu)
+            .add_systems(OnExit(GameState::Controls), (cleanup_menu, save_bindings))
+            .add_systems(OnEnter(PauseState::Controls), setup_controls_menu)
+            .add_systems(OnExit(PauseState::Controls), (cleanup_menu, save_bindings))
             .add_systems(
                 Update,
                 (
                     button_system.run_if(in_state(GameState::MainMenu)),
-                    generate_button_system.run_if(in_state(GameState::GenerateMenu)),
+                    (generate_button_system, seed_input, update_gen_menu)
+                        .chain()
+                        .run_if(in_state(GameState::GenerateMenu)),
+                    win_button_system.run_if(in_state(GameState::Won)),
+                    toggle_pause.run_if(in_state(GameState::InGame)),
+                    pause_button_system.run_if(in_state(PauseState::Paused)),
+                    (settings_button_system, update_settings_menu)
+                        .chain()
+                        .run_if(in_state(GameState::Settings).or_else(in_state(PauseState::Settings))),
+                    (controls_button_system, update_controls_menu)
+                        .chain()
+                        .run_if(in_state(GameState::Controls).or_else(in_state(PauseState::Controls))),
                 ),
             )
             .add_systems(OnExit(GameState::MainMenu), cleanup_menu)
             .add_systems(OnExit(GameState::GenerateMenu), cleanup_menu)
+            .add_systems(OnExit(GameState::Won), cleanup_menu)
+            .add_systems(OnExit(GameState::Settings), (cleanup_menu, save_settings))
             .add_systems(OnExit(GameState::InGame), setup_ui_camera)
             .add_systems(OnEnter(GameState::InGame), remove_ui_camera);
     }
@@ -43,9 +137,150 @@ struct MainMenu;
 #[derive(Component)]
 struct GenerateMenu;
 
+#[derive(Component)]
+struct WinMenu;
+
+#[derive(Component)]
+struct PauseMenu;
+
+#[derive(Component)]
+struct SettingsMenu;
+
+#[derive(Component)]
+struct ControlsMenu;
+
+// Text of the button showing what an action is bound to
+#[derive(Component)]
+struct BindingText(Action, BindSlot);
+
+// Action waiting for the next key or gamepad button press
+#[derive(Resource, Default)]
+struct Rebinding(Option<(Action, BindSlot)>);
+
+// Button the keyboard or gamepad is on
+#[derive(Component)]
+struct Focused;
+
+// Pressed from the keyboard or gamepad, released again on the next frame
+#[derive(Component)]
+struct NavPressed;
+
+// Text on the settings screen showing one of the game settings
+#[derive(Component, Clone, Copy)]
+enum SettingValue {
+    Quality,
+    Shadows,
+    Flashlight,
+    Sensitivity,
+    Camera,
+    FogOfWar,
+    ZoomMin,
+    ZoomMax,
+    Volume,
+    Fullscreen,
+    Vsync,
+}
+
 #[derive(Component)]
 struct UiCamera;
 
+// Text on the generate screen showing one of the settings
+#[derive(Component, Clone, Copy)]
+enum GenValue {
+    Rows,
+    Cols,
+    Algorithm,
+    Seed,
+    Braid,
+    Difficulty,
+}
+
+#[derive(Component)]
+struct MazePreview;
+
+const PREVIEW_SIZE: f32 = 320.0;
+// Range of the size steppers, kept odd and above the smallest maze the generators build
+const MENU_MIN_SIZE: usize = 5;
+const MENU_MAX_SIZE: usize = 99;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Difficulty {
+    Easy,
+    Normal,
+    Hard,
+    Custom,
+}
+
+impl Difficulty {
+    fn next(self) -> Difficulty {
+        match self {
+            Difficulty::Easy => Difficulty::Normal,
+            Difficulty::Normal => Difficulty::Hard,
+            Difficulty::Hard | Difficulty::Custom => Difficulty::Easy,
+        }
+    }
+
+    // Size and braid percentage
+    fn preset(self) -> Option<(usize, u32)> {
+        match self {
+            Difficulty::Easy => Some((15, 50)),
+            Difficulty::Normal => Some((21, 10)),
+            Difficulty::Hard => Some((41, 0)),
+            Difficulty::Custom => None,
+        }
+    }
+}
+
+// Options picked on the generate screen, also used by "New Maze"
+#[derive(Resource, Debug, Clone)]
+pub struct GenSettings {
+    pub rows: usize,
+    pub cols: usize,
+    pub algorithm: Algorithm,
+    // Kept as typed, empty means a random seed
+    pub seed: String,
+    // The seed used while `seed` is empty, drawn up front so the preview shows the maze Play builds
+    pub random_seed: u64,
+    // Percentage of dead ends opened up into loops
+    pub braid: u32,
+    pub difficulty: Difficulty,
+}
+
+impl Default for GenSettings {
+    fn default() -> Self {
+        GenSettings {
+            rows: 21,
+            cols: 21,
+            algorithm: Algorithm::default(),
+            seed: rand::random::<u32>().to_string(),
+            random_seed: rand::random(),
+            braid: 10,
+            difficulty: Difficulty::Normal,
+        }
+    }
+}
+
+impl GenSettings {
+    pub fn seed(&self) -> u64 {
+        self.seed.parse().unwrap_or(self.random_seed)
+    }
+
+    pub fn build(&self, seed: u64) -> Vec<Vec<char>> {
+        let mut mat = self.algorithm.generate(self.rows, self.cols, seed);
+        algo::braid(&mut mat, self.braid as f32 / 100.0, seed);
+        mat
+    }
+
+    fn set_difficulty(&mut self, difficulty: Difficulty) {
+        self.difficulty = difficulty;
+        if let Some((size, braid)) = difficulty.preset() {
+            self.rows = size;
+            self.cols = size;
+            self.braid = braid;
+        }
+    }
+}
+
 #[derive(Resource)]
 pub struct UiCameraExists(pub bool);
 
@@ -54,9 +289,42 @@ enum MenuButton {
     Play,
     Generate,
     Exit,
-    Option1,
-    Option2,
-    Option3,
+    Back,
+    RowsDown,
+    RowsUp,
+    ColsDown,
+    ColsUp,
+    NextAlgorithm,
+    RandomSeed,
+    BraidDown,
+    BraidUp,
+    NextDifficulty,
+    Settings,
+    NextQuality,
+    ToggleShadows,
+    FlashlightDown,
+    FlashlightUp,
+    SensitivityDown,
+    SensitivityUp,
+    ToggleCamera,
+    ToggleFogOfWar,
+    ZoomMinDown,
+    ZoomMinUp,
+    ZoomMaxDown,
+    ZoomMaxUp,
+    VolumeDown,
+    VolumeUp,
+    ToggleFullscreen,
+    ToggleVsync,
+    Controls,
+    Bind(Action, BindSlot),
+    ResetBindings,
+    Replay,
+    NewMaze,
+    BackToMenu,
+    Resume,
+    Restart,
+    ShowSolution,
 }
 fn setup_ui_camera(
     mut commands: Commands,
@@ -124,12 +392,22 @@ fn setup_menu(mut commands: Commands, asset_server: Res<AssetServer>) {
             // Generate button
             spawn_button(parent, "Generate", MenuButton::Generate, &asset_server);
 
+            // Settings button
+            spawn_button(parent, "Settings", MenuButton::Settings, &asset_server);
+
             // Exit button
             spawn_button(parent, "Exit", MenuButton::Exit, &asset_server);
         });
 }
 
-fn setup_gen_menu(mut commands: Commands, asset_server: Res<AssetServer>) {
+fn setup_gen_menu(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut images: ResMut<Assets<Image>>,
+    settings: Res<GenSettings>,
+) {
+    let preview = images.add(preview_image(&settings));
+
     commands
         .spawn((
             NodeBundle {
@@ -149,7 +427,7 @@ fn setup_gen_menu(mut commands: Commands, asset_server: Res<AssetServer>) {
         .with_children(|parent| {
             // Title
             parent.spawn(TextBundle::from_section(
-                "Generate Options",
+                "Generate Maze",
                 TextStyle {
                     font: asset_server.load("fonts/FiraSans-Bold.ttf"),
                     font_size: 64.0,
@@ -157,17 +435,199 @@ fn setup_gen_menu(mut commands: Commands, asset_server: Res<AssetServer>) {
                 },
             ));
 
-            // Generate Option 1
-            spawn_button(parent, "Prim's Algo", MenuButton::Option1, &asset_server);
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Row,
+                        align_items: AlignItems::Center,
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|parent| {
+                    // Settings
+                    parent
+                        .spawn(NodeBundle {
+                            style: Style {
+                                flex_direction: FlexDirection::Column,
+                                ..default()
+                            },
+                            ..default()
+                        })
+                        .with_children(|parent| {
+                            let rows = [
+                                ("Rows", GenValue::Rows, Some(MenuButton::RowsDown), (MenuButton::RowsUp, "+")),
+                                ("Columns", GenValue::Cols, Some(MenuButton::ColsDown), (MenuButton::ColsUp, "+")),
+                                ("Algorithm", GenValue::Algorithm, None, (MenuButton::NextAlgorithm, ">")),
+                                ("Seed", GenValue::Seed, None, (MenuButton::RandomSeed, "Random")),
+                                ("Braid", GenValue::Braid, Some(MenuButton::BraidDown), (MenuButton::BraidUp, "+")),
+                                ("Difficulty", GenValue::Difficulty, None, (MenuButton::NextDifficulty, ">")),
+                            ];
+                            for (label, value, down, up) in rows {
+                                spawn_setting(parent, label, value, gen_value_text(value, &settings), down, up, &asset_server);
+                            }
+                        });
 
-            // Generate Option 2
-            spawn_button(parent, "Option 2", MenuButton::Option2, &asset_server);
+                    // Preview
+                    let (width, height) = preview_size(&settings);
+                    parent.spawn((
+                        ImageBundle {
+                            style: Style {
+                                width: Val::Px(width),
+                                height: Val::Px(height),
+                                margin: UiRect::all(Val::Px(20.0)),
+                                ..default()
+                            },
+                            image: UiImage::new(preview),
+                            ..default()
+                        },
+                        MazePreview,
+                    ));
+                });
 
-            // Generate Option 3
-            spawn_button(parent, "Option 3", MenuButton::Option3, &asset_server);
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Row,
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|parent| {
+                    spawn_button(parent, "Back", MenuButton::Back, &asset_server);
+                    spawn_button(parent, "Play", MenuButton::Play, &asset_server);
+                });
         });
 }
 
+// Label, optional "-" button, current value and a button that changes it
+fn spawn_setting(
+    parent: &mut ChildBuilder,
+    label: &str,
+    value: impl Component,
+    text: String,
+    down: Option<MenuButton>,
+    (up, up_text): (MenuButton, &str),
+    asset_server: &Res<AssetServer>,
+) {
+    let text_style = TextStyle {
+        font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+        font_size: 28.0,
+        color: Color::WHITE,
+    };
+
+    parent
+        .spawn(NodeBundle {
+            style: Style {
+                flex_direction: FlexDirection::Row,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|parent| {
+            parent.spawn(
+                TextBundle::from_section(label, text_style.clone()).with_style(Style {
+                    width: Val::Px(160.0),
+                    ..default()
+                }),
+            );
+
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        width: Val::Px(60.0),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|parent| {
+                    if let Some(down) = down {
+                        spawn_small_button(parent, "-", down, asset_server);
+                    }
+                });
+
+            parent.spawn((
+                TextBundle::from_section(text, text_style).with_style(Style {
+                    width: Val::Px(180.0),
+                    ..default()
+                }),
+                value,
+            ));
+
+            spawn_small_button(parent, up_text, up, asset_server);
+        });
+}
+
+fn spawn_small_button(
+    parent: &mut ChildBuilder,
+    text: &str,
+    button_type: MenuButton,
+    asset_server: &Res<AssetServer>,
+) {
+    parent
+        .spawn((
+            ButtonBundle {
+                style: Style {
+                    min_width: Val::Px(50.0),
+                    height: Val::Px(40.0),
+                    margin: UiRect::all(Val::Px(4.0)),
+                    padding: UiRect::horizontal(Val::Px(10.0)),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                background_color: Color::srgb(0.15, 0.15, 0.15).into(),
+                ..default()
+            },
+            button_type,
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                text,
+                TextStyle {
+                    font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                    font_size: 28.0,
+                    color: Color::WHITE,
+                },
+            ));
+        });
+}
+
+fn gen_value_text(value: GenValue, settings: &GenSettings) -> String {
+    match value {
+        GenValue::Rows => settings.rows.to_string(),
+        GenValue::Cols => settings.cols.to_string(),
+        GenValue::Algorithm => settings.algorithm.to_string(),
+        GenValue::Seed if settings.seed.is_empty() => "random".to_string(),
+        GenValue::Seed => settings.seed.clone(),
+        GenValue::Braid => format!("{}%", settings.braid),
+        GenValue::Difficulty => format!("{:?}", settings.difficulty),
+    }
+}
+
+// One pixel per tile, scaled up without smoothing by the UI
+fn preview_image(settings: &GenSettings) -> Image {
+    let mat = settings.build(settings.seed());
+    let options = export::ExportOptions { cell_size: 1, ..default() };
+    let (width, height, pixels) = export::to_rgba(&mat, &options);
+
+    let mut image = Image::new(
+        Extent3d { width, height, depth_or_array_layers: 1 },
+        TextureDimension::D2,
+        pixels,
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::default(),
+    );
+    image.sampler = ImageSampler::nearest();
+    image
+}
+
+fn preview_size(settings: &GenSettings) -> (f32, f32) {
+    let scale = PREVIEW_SIZE / settings.rows.max(settings.cols) as f32;
+    (settings.cols as f32 * scale, settings.rows as f32 * scale)
+}
+
 fn spawn_button(
     parent: &mut ChildBuilder,
     text: &str,
@@ -202,12 +662,17 @@ fn spawn_button(
         });
 }
 
+// Menu buttons that were pressed or hovered this frame
+type ButtonInteractions<'w, 's> = Query<
+    'w,
+    's,
+    (&'static Interaction, &'static MenuButton, &'static mut BackgroundColor),
+    (Changed<Interaction>, With<Button>),
+>;
+
 fn button_system(
     mut next_state: ResMut<NextState<GameState>>,
-    mut interaction_query: Query<
-        (&Interaction, &MenuButton, &mut BackgroundColor),
-        (Changed<Interaction>, With<Button>),
-    >,
+    mut interaction_query: ButtonInteractions,
     mut app_exit_events: EventWriter<bevy::app::AppExit>,
 ) {
     for (interaction, button, mut color) in &mut interaction_query {
@@ -222,6 +687,9 @@ fn button_system(
                 MenuButton::Generate => {
                     next_state.set(GameState::GenerateMenu);
                 }
+                MenuButton::Settings => {
+                    next_state.set(GameState::Settings);
+                }
                 _ => {}
             },
             Interaction::Hovered => {
@@ -236,31 +704,677 @@ fn button_system(
 
 fn generate_button_system(
     mut next_state: ResMut<NextState<GameState>>,
-    mut interaction_query: Query<
-        (&Interaction, &MenuButton, &mut BackgroundColor),
-        (Changed<Interaction>, With<Button>),
-    >,
+    mut origin: ResMut<MazeOrigin>,
+    mut settings: ResMut<GenSettings>,
+    mut interaction_query: ButtonInteractions,
 ) {
     for (interaction, button, mut color) in &mut interaction_query {
         match *interaction {
-            Interaction::Pressed => {
-                match button {
-                    MenuButton::Option1 => {
-                        println!("Generate option selected: {:?}", button);
-                        // Here you can add logic to handle the selected generation option
-                        let mat = algo::Prim::new(20, 20);
-                        
-                        let res = writefunc(mat, "assets/map.txt");
-                        match res {
-                            Ok(()) => {},
-                            Err(e) => {println!("{}", e)},
-                        }
-                        
-                        next_state.set(GameState::MainMenu);
-                    }
-                    _ => {}
+            Interaction::Pressed => match button {
+                MenuButton::Play => {
+                    let seed = settings.seed();
+                    generate_maze(&settings, seed, &mut origin);
+                    // The next random maze shouldn't repeat this one
+                    settings.random_seed = rand::random();
+                    next_state.set(GameState::InGame);
+                }
+                MenuButton::Back => {
+                    next_state.set(GameState::MainMenu);
+                }
+                MenuButton::RowsDown | MenuButton::RowsUp | MenuButton::ColsDown | MenuButton::ColsUp => {
+                    // Odd sizes leave no double thick walls along the far edges
+                    let (size, step) = match button {
+                        MenuButton::RowsDown => (&mut settings.rows, -2),
+                        MenuButton::RowsUp => (&mut settings.rows, 2),
+                        MenuButton::ColsDown => (&mut settings.cols, -2),
+                        _ => (&mut settings.cols, 2),
+                    };
+                    *size = size.saturating_add_signed(step).clamp(MENU_MIN_SIZE, MENU_MAX_SIZE);
+                    settings.difficulty = Difficulty::Custom;
+                }
+                MenuButton::NextAlgorithm => {
+                    let index = Algorithm::ALL.iter().position(|&a| a == settings.algorithm).unwrap_or(0);
+                    settings.algorithm = Algorithm::ALL[(index + 1) % Algorithm::ALL.len()];
+                }
+                MenuButton::RandomSeed => {
+                    settings.seed = rand::random::<u32>().to_string();
+                }
+                MenuButton::BraidDown => {
+                    settings.braid = settings.braid.saturating_sub(10);
+                    settings.difficulty = Difficulty::Custom;
+                }
+                MenuButton::BraidUp => {
+                    settings.braid = (settings.braid + 10).min(100);
+                    settings.difficulty = Difficulty::Custom;
+                }
+                MenuButton::NextDifficulty => {
+                    let difficulty = settings.difficulty.next();
+                    settings.set_difficulty(difficulty);
+                }
+                _ => {}
+            },
+            Interaction::Hovered => {
+                *color = Color::srgb(0.25, 0.25, 0.25).into();
+            }
+            Interaction::None => {
+                *color = Color::srgb(0.15, 0.15, 0.15).into();
+            }
+        }
+    }
+}
+
+// Digits typed on the generate screen go into the seed
+fn seed_input(mut events: EventReader<KeyboardInput>, mut settings: ResMut<GenSettings>) {
+    for event in events.read() {
+        if event.state != ButtonState::Pressed {
+            continue;
+        }
+        match &event.logical_key {
+            Key::Character(c) if c.chars().all(|c| c.is_ascii_digit()) => {
+                let seed = format!("{}{}", settings.seed, c);
+                // Stop before the seed no longer fits in a u64
+                if seed.parse::<u64>().is_ok() {
+                    settings.seed = seed;
+                }
+            }
+            Key::Backspace => {
+                settings.seed.pop();
+            }
+            _ => {}
+        }
+    }
+}
+
+fn update_gen_menu(
+    settings: Res<GenSettings>,
+    mut images: ResMut<Assets<Image>>,
+    mut text_q: Query<(&mut Text, &GenValue)>,
+    mut preview_q: Query<(&UiImage, &mut Style), With<MazePreview>>,
+) {
+    if !settings.is_changed() {
+        return;
+    }
+
+    for (mut text, value) in text_q.iter_mut() {
+        text.sections[0].value = gen_value_text(*value, &settings);
+    }
+
+    for (ui_image, mut style) in preview_q.iter_mut() {
+        if let Some(image) = images.get_mut(&ui_image.texture) {
+            *image = preview_image(&settings);
+        }
+        let (width, height) = preview_size(&settings);
+        style.width = Val::Px(width);
+        style.height = Val::Px(height);
+    }
+}
+
+fn setup_win_menu(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    stats: Res<RunStats>,
+    explored: Res<Explored>,
+    maze: Res<Maze>,
+) {
+    let path = match stats.optimal {
+        Some(optimal) => format!(
+            "Path: {:.1} tiles (optimal {}, {:.0}% efficient)",
+            stats.distance,
+            optimal,
+            100.0 * stats.efficiency(optimal),
+        ),
+        None => format!("Path: {:.1} tiles", stats.distance),
+    };
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    flex_direction: FlexDirection::Column,
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                background_color: Color::srgb(0.1, 0.1, 0.1).into(),
+                ..default()
+            },
+            WinMenu,
+        ))
+        .with_children(|parent| {
+            // Title
+            parent.spawn(TextBundle::from_section(
+                "You escaped!",
+                TextStyle {
+                    font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                    font_size: 64.0,
+                    color: Color::WHITE,
+                },
+            ));
+
+            let lines = [
+                format!("Time: {}", format_time(stats.elapsed)),
+                path,
+                format!("Explored {:.0}% of the maze", 100.0 * explored.fraction(&maze.0)),
+            ];
+            for line in lines {
+                parent.spawn(TextBundle::from_section(
+                    line,
+                    TextStyle {
+                        font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                        font_size: 32.0,
+                        color: Color::WHITE,
+                    },
+                ));
+            }
+
+            spawn_button(parent, "Replay", MenuButton::Replay, &asset_server);
+            spawn_button(parent, "New Maze", MenuButton::NewMaze, &asset_server);
+            spawn_button(parent, "Main Menu", MenuButton::BackToMenu, &asset_server);
+        });
+}
+
+fn win_button_system(
+    mut next_state: ResMut<NextState<GameState>>,
+    mut origin: ResMut<MazeOrigin>,
+    settings: Res<GenSettings>,
+    mut interaction_query: ButtonInteractions,
+) {
+    for (interaction, button, mut color) in &mut interaction_query {
+        match *interaction {
+            Interaction::Pressed => match button {
+                MenuButton::Replay => {
+                    next_state.set(GameState::InGame);
+                }
+                MenuButton::NewMaze => {
+                    generate_maze(&settings, rand::random(), &mut origin);
+                    next_state.set(GameState::InGame);
+                }
+                MenuButton::BackToMenu => {
+                    next_state.set(GameState::MainMenu);
+                }
+                _ => {}
+            },
+            Interaction::Hovered => {
+                *color = Color::srgb(0.25, 0.25, 0.25).into();
+            }
+            Interaction::None => {
+                *color = Color::srgb(0.15, 0.15, 0.15).into();
+            }
+        }
+    }
+}
+
+fn start_game(mut next_state: ResMut<NextState<GameState>>) {
+    next_state.set(GameState::InGame);
+}
+
+fn toggle_pause(
+    actions: Actions,
+    state: Res<State<PauseState>>,
+    mut next_state: ResMut<NextState<PauseState>>,
+) {
+    if !actions.just_pressed(Action::Pause) {
+        return;
+    }
+    next_state.set(match state.get() {
+        PauseState::Running => PauseState::Paused,
+        PauseState::Paused => PauseState::Running,
+        PauseState::Settings => PauseState::Paused,
+        PauseState::Controls => PauseState::Settings,
+        // The flythrough skips itself
+        PauseState::Flythrough => return,
+    });
+}
+
+// Freezes game time and physics, and frees the cursor for the menu
+fn pause_game(
+    mut time: ResMut<Time<Virtual>>,
+    mut rapier_config: ResMut<RapierConfiguration>,
+    mut cam_q: Query<&mut ThirdPersonCamera>,
+) {
+    time.pause();
+    rapier_config.physics_pipeline_active = false;
+    for mut cam in cam_q.iter_mut() {
+        cam.cursor_lock_active = false;
+    }
+}
+
+fn resume_game(
+    mut time: ResMut<Time<Virtual>>,
+    mut rapier_config: ResMut<RapierConfiguration>,
+    mut cam_q: Query<&mut ThirdPersonCamera>,
+) {
+    time.unpause();
+    rapier_config.physics_pipeline_active = true;
+    for mut cam in cam_q.iter_mut() {
+        cam.cursor_lock_active = true;
+    }
+}
+
+fn setup_pause_menu(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    flex_direction: FlexDirection::Column,
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                // See-through, so the paused maze stays visible behind the menu
+                background_color: Color::srgba(0.1, 0.1, 0.1, 0.7).into(),
+                ..default()
+            },
+            PauseMenu,
+        ))
+        .with_children(|parent| {
+            // Title
+            parent.spawn(TextBundle::from_section(
+                "Paused",
+                TextStyle {
+                    font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                    font_size: 64.0,
+                    color: Color::WHITE,
+                },
+            ));
+
+            spawn_button(parent, "Resume", MenuButton::Resume, &asset_server);
+            spawn_button(parent, "Restart", MenuButton::Restart, &asset_server);
+            spawn_button(parent, "New Maze", MenuButton::NewMaze, &asset_server);
+            spawn_button(parent, "Solution", MenuButton::ShowSolution, &asset_server);
+            spawn_button(parent, "Settings", MenuButton::Settings, &asset_server);
+            spawn_button(parent, "Main Menu", MenuButton::BackToMenu, &asset_server);
+        });
+}
+
+fn pause_button_system(
+    mut commands: Commands,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut next_pause_state: ResMut<NextState<PauseState>>,
+    mut origin: ResMut<MazeOrigin>,
+    settings: Res<GenSettings>,
+    mut interaction_query: ButtonInteractions,
+) {
+    for (interaction, button, mut color) in &mut interaction_query {
+        match *interaction {
+            Interaction::Pressed => match button {
+                MenuButton::Resume => {
+                    next_pause_state.set(PauseState::Running);
+                }
+                MenuButton::Restart => {
+                    next_state.set(GameState::Loading);
+                }
+                MenuButton::Settings => {
+                    next_pause_state.set(PauseState::Settings);
+                }
+                MenuButton::ShowSolution => {
+                    commands.insert_resource(AfterFlythrough::Paused);
+                    next_pause_state.set(PauseState::Flythrough);
+                }
+                MenuButton::NewMaze => {
+                    generate_maze(&settings, rand::random(), &mut origin);
+                    next_state.set(GameState::Loading);
+                }
+                MenuButton::BackToMenu => {
+                    next_state.set(GameState::MainMenu);
+                }
+                _ => {}
+            },
+            Interaction::Hovered => {
+                *color = Color::srgb(0.25, 0.25, 0.25).into();
+            }
+            Interaction::None => {
+                *color = Color::srgb(0.15, 0.15, 0.15).into();
+            }
+        }
+    }
+}
+
+fn setup_settings_menu(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    settings: Res<GameSettings>,
+    state: Res<State<GameState>>,
+) {
+    // See-through when opened from the pause menu, like the pause menu itself
+    let background = match state.get() {
+        GameState::InGame => Color::srgba(0.1, 0.1, 0.1, 0.7),
+        _ => Color::srgb(0.1, 0.1, 0.1),
+    };
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    flex_direction: FlexDirection::Column,
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                background_color: background.into(),
+                ..default()
+            },
+            SettingsMenu,
+        ))
+        .with_children(|parent| {
+            // Title
+            parent.spawn(TextBundle::from_section(
+                "Settings",
+                TextStyle {
+                    font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                    font_size: 64.0,
+                    color: Color::WHITE,
+                },
+            ));
+
+            let rows = [
+                ("Quality", SettingValue::Quality, None, (MenuButton::NextQuality, ">")),
+                ("Shadows", SettingValue::Shadows, None, (MenuButton::ToggleShadows, ">")),
+                ("Flashlight", SettingValue::Flashlight, Some(MenuButton::FlashlightDown), (MenuButton::FlashlightUp, "+")),
+                ("Mouse", SettingValue::Sensitivity, Some(MenuButton::SensitivityDown), (MenuButton::SensitivityUp, "+")),
+                ("Camera", SettingValue::Camera, None, (MenuButton::ToggleCamera, ">")),
+                ("Fog of war", SettingValue::FogOfWar, None, (MenuButton::ToggleFogOfWar, ">")),
+                ("Zoom min", SettingValue::ZoomMin, Some(MenuButton::ZoomMinDown), (MenuButton::ZoomMinUp, "+")),
+                ("Zoom max", SettingValue::ZoomMax, Some(MenuButton::ZoomMaxDown), (MenuButton::ZoomMaxUp, "+")),
+                ("Volume", SettingValue::Volume, Some(MenuButton::VolumeDown), (MenuButton::VolumeUp, "+")),
+                ("Fullscreen", SettingValue::Fullscreen, None, (MenuButton::ToggleFullscreen, ">")),
+                ("VSync", SettingValue::Vsync, None, (MenuButton::ToggleVsync, ">")),
+            ];
+            for (label, value, down, up) in rows {
+                spawn_setting(parent, label, value, setting_value_text(value, &settings), down, up, &asset_server);
+            }
+
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Row,
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|parent| {
+                    spawn_button(parent, "Back", MenuButton::Back, &asset_server);
+                    spawn_button(parent, "Controls", MenuButton::Controls, &asset_server);
+                });
+        });
+}
+
+fn setting_value_text(value: SettingValue, settings: &GameSettings) -> String {
+    let on_off = |on: bool| if on { "On" } else { "Off" }.to_string();
+    match value {
+        SettingValue::Quality => format!("{:?}", settings.quality),
+        SettingValue::Shadows => on_off(settings.shadows),
+        SettingValue::Flashlight => format!("{:.0}%", 100.0 * settings.flashlight_intensity / GameSettings::default().flashlight_intensity),
+        SettingValue::Sensitivity => format!("{:.1}x", settings.mouse_sensitivity),
+        SettingValue::Camera => match settings.camera {
+            CameraMode::ThirdPerson => "Third person".to_string(),
+            CameraMode::FirstPerson => "First person".to_string(),
+        },
+        SettingValue::FogOfWar => on_off(settings.hide_unexplored),
+        SettingValue::ZoomMin => format!("{:.1}", settings.zoom_min),
+        SettingValue::ZoomMax => format!("{:.1}", settings.zoom_max),
+        SettingValue::Volume => format!("{:.0}%", 100.0 * settings.volume),
+        SettingValue::Fullscreen => on_off(settings.fullscreen),
+        SettingValue::Vsync => on_off(settings.vsync),
+    }
+}
+
+fn settings_button_system(
+    state: Res<State<GameState>>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut next_pause_state: ResMut<NextState<PauseState>>,
+    mut settings: ResMut<GameSettings>,
+    mut interaction_query: ButtonInteractions,
+) {
+    let flashlight_step = GameSettings::default().flashlight_intensity / 10.0;
+
+    for (interaction, button, mut color) in &mut interaction_query {
+        match *interaction {
+            Interaction::Pressed => match button {
+                MenuButton::Back => match state.get() {
+                    GameState::InGame => next_pause_state.set(PauseState::Paused),
+                    _ => next_state.set(GameState::MainMenu),
+                },
+                MenuButton::Controls => match state.get() {
+                    GameState::InGame => next_pause_state.set(PauseState::Controls),
+                    _ => next_state.set(GameState::Controls),
+                },
+                MenuButton::NextQuality => {
+                    settings.quality = settings.quality.next();
+                }
+                MenuButton::ToggleShadows => {
+                    settings.shadows = !settings.shadows;
+                }
+                MenuButton::FlashlightDown => {
+                    settings.flashlight_intensity = (settings.flashlight_intensity - flashlight_step).max(0.0);
+                }
+                MenuButton::FlashlightUp => {
+                    settings.flashlight_intensity = (settings.flashlight_intensity + flashlight_step).min(30.0 * flashlight_step);
+                }
+                MenuButton::SensitivityDown => {
+                    settings.mouse_sensitivity = (settings.mouse_sensitivity - 0.1).max(0.1);
+                }
+                MenuButton::SensitivityUp => {
+                    settings.mouse_sensitivity = (settings.mouse_sensitivity + 0.1).min(3.0);
+                }
+                MenuButton::ToggleCamera => {
+                    settings.camera = settings.camera.toggled();
+                }
+                MenuButton::ToggleFogOfWar => {
+                    settings.hide_unexplored = !settings.hide_unexplored;
+                }
+                // The camera can't zoom closer than its minimum or farther than its maximum
+                MenuButton::ZoomMinDown => {
+                    settings.zoom_min = (settings.zoom_min - 0.5).max(0.5);
+                }
+                MenuButton::ZoomMinUp => {
+                    settings.zoom_min = (settings.zoom_min + 0.5).min(settings.zoom_max);
+                }
+                MenuButton::ZoomMaxDown => {
+                    settings.zoom_max = (settings.zoom_max - 0.5).max(settings.zoom_min);
                 }
+                MenuButton::ZoomMaxUp => {
+                    settings.zoom_max = (settings.zoom_max + 0.5).min(10.0);
+                }
+                MenuButton::VolumeDown => {
+                    settings.volume = (settings.volume - 0.1).max(0.0);
+                }
+                MenuButton::VolumeUp => {
+                    settings.volume = (settings.volume + 0.1).min(1.0);
+                }
+                MenuButton::ToggleFullscreen => {
+                    settings.fullscreen = !settings.fullscreen;
+                }
+                MenuButton::ToggleVsync => {
+                    settings.vsync = !settings.vsync;
+                }
+                _ => {}
+            },
+            Interaction::Hovered => {
+                *color = Color::srgb(0.25, 0.25, 0.25).into();
+            }
+            Interaction::None => {
+                *color = Color::srgb(0.15, 0.15, 0.15).into();
             }
+        }
+    }
+}
+
+fn update_settings_menu(settings: Res<GameSettings>, mut text_q: Query<(&mut Text, &SettingValue)>) {
+    if !settings.is_changed() {
+        return;
+    }
+
+    for (mut text, value) in text_q.iter_mut() {
+        text.sections[0].value = setting_value_text(*value, &settings);
+    }
+}
+
+fn save_settings(settings: Res<GameSettings>) {
+    if let Err(e) = settings.save() {
+        println!("Could not save settings: {}", e);
+    }
+}
+
+fn setup_controls_menu(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    bindings: Res<Bindings>,
+    state: Res<State<GameState>>,
+) {
+    let background = match state.get() {
+        GameState::InGame => Color::srgba(0.1, 0.1, 0.1, 0.7),
+        _ => Color::srgb(0.1, 0.1, 0.1),
+    };
+    let text_style = TextStyle {
+        font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+        font_size: 28.0,
+        color: Color::WHITE,
+    };
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    flex_direction: FlexDirection::Column,
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                background_color: background.into(),
+                ..default()
+            },
+            ControlsMenu,
+        ))
+        .with_children(|parent| {
+            // Title
+            parent.spawn(TextBundle::from_section(
+                "Controls",
+                TextStyle {
+                    font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                    font_size: 64.0,
+                    color: Color::WHITE,
+                },
+            ));
+
+            // Two columns of actions, each with a keyboard and a gamepad binding
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Column,
+                        flex_wrap: FlexWrap::Wrap,
+                        height: Val::Px(8.0 * 48.0),
+                        column_gap: Val::Px(40.0),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|parent| {
+                    for action in Action::ALL {
+                        parent
+                            .spawn(NodeBundle {
+                                style: Style {
+                                    flex_direction: FlexDirection::Row,
+                                    align_items: AlignItems::Center,
+                                    ..default()
+                                },
+                                ..default()
+                            })
+                            .with_children(|parent| {
+                                parent.spawn(
+                                    TextBundle::from_section(action.label(), text_style.clone()).with_style(Style {
+                                        width: Val::Px(150.0),
+                                        ..default()
+                                    }),
+                                );
+                                for slot in [BindSlot::Key, BindSlot::Gamepad] {
+                                    spawn_binding_button(parent, action, slot, &bindings, &text_style);
+                                }
+                            });
+                    }
+                });
+
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Row,
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|parent| {
+                    spawn_button(parent, "Back", MenuButton::Back, &asset_server);
+                    spawn_button(parent, "Reset", MenuButton::ResetBindings, &asset_server);
+                });
+        });
+}
+
+fn spawn_binding_button(
+    parent: &mut ChildBuilder,
+    action: Action,
+    slot: BindSlot,
+    bindings: &Bindings,
+    text_style: &TextStyle,
+) {
+    parent
+        .spawn((
+            ButtonBundle {
+                style: Style {
+                    width: Val::Px(140.0),
+                    height: Val::Px(40.0),
+                    margin: UiRect::all(Val::Px(4.0)),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                background_color: Color::srgb(0.15, 0.15, 0.15).into(),
+                ..default()
+            },
+            MenuButton::Bind(action, slot),
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(bindings.describe(action, slot), text_style.clone()),
+                BindingText(action, slot),
+            ));
+        });
+}
+
+fn controls_button_system(
+    state: Res<State<GameState>>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut next_pause_state: ResMut<NextState<PauseState>>,
+    mut bindings: ResMut<Bindings>,
+    mut rebinding: ResMut<Rebinding>,
+    mut interaction_query: ButtonInteractions,
+) {
+    for (interaction, button, mut color) in &mut interaction_query {
+        match *interaction {
+            Interaction::Pressed => match button {
+                MenuButton::Back => {
+                    rebinding.0 = None;
+                    match state.get() {
+                        GameState::InGame => next_pause_state.set(PauseState::Settings),
+                        _ => next_state.set(GameState::Settings),
+                    }
+                }
+                MenuButton::Bind(action, slot) => {
+                    rebinding.0 = Some((*action, *slot));
+                }
+                MenuButton::ResetBindings => {
+                    rebinding.0 = None;
+                    *bindings = Bindings::default();
+                }
+                _ => {}
+            },
             Interaction::Hovered => {
                 *color = Color::srgb(0.25, 0.25, 0.25).into();
             }
@@ -271,27 +1385,241 @@ fn generate_button_system(
     }
 }
 
+// Binds the next key or gamepad button pressed, and swallows that press so nothing else reacts to it
+fn capture_binding(
+    mut rebinding: ResMut<Rebinding>,
+    mut bindings: ResMut<Bindings>,
+    mut keys: ResMut<ButtonInput<KeyCode>>,
+    mut pad_buttons: ResMut<ButtonInput<GamepadButton>>,
+) {
+    let Some((action, slot)) = rebinding.0 else {
+        return;
+    };
+
+    match slot {
+        BindSlot::Key => {
+            let Some(&key) = keys.get_just_pressed().next() else {
+                return;
+            };
+            keys.clear_just_pressed(key);
+            bindings.rebind_key(action, key);
+        }
+        BindSlot::Gamepad => {
+            let Some(&button) = pad_buttons.get_just_pressed().next() else {
+                return;
+            };
+            pad_buttons.clear_just_pressed(button);
+            bindings.rebind_gamepad(action, button.button_type);
+        }
+    }
+    rebinding.0 = None;
+}
+
+fn update_controls_menu(
+    bindings: Res<Bindings>,
+    rebinding: Res<Rebinding>,
+    mut text_q: Query<(&mut Text, &BindingText)>,
+) {
+    if !bindings.is_changed() && !rebinding.is_changed() {
+        return;
+    }
+
+    for (mut text, BindingText(action, slot)) in text_q.iter_mut() {
+        text.sections[0].value = match rebinding.0 {
+            Some(waiting) if waiting == (*action, *slot) => "press...".to_string(),
+            _ => bindings.describe(*action, *slot),
+        };
+    }
+}
+
+fn save_bindings(bindings: Res<Bindings>) {
+    if let Err(e) = bindings.save() {
+        println!("Could not save key bindings: {}", e);
+    }
+}
+
+fn release_nav_press(mut commands: Commands, mut pressed_q: Query<(Entity, &mut Interaction), With<NavPressed>>) {
+    for (entity, mut interaction) in pressed_q.iter_mut() {
+        *interaction = Interaction::None;
+        commands.entity(entity).remove::<NavPressed>();
+    }
+}
+
+// Every screen with buttons on it, but not the game itself or the flythrough
+fn in_menu(game_state: Res<State<GameState>>, pause_state: Option<Res<State<PauseState>>>) -> bool {
+    match game_state.get() {
+        GameState::InGame => pause_state.is_some_and(|state| {
+            matches!(state.get(), PauseState::Paused | PauseState::Settings | PauseState::Controls)
+        }),
+        GameState::Loading => false,
+        _ => true,
+    }
+}
+
+// Menu up/down/left/right and the left stick move between buttons, Confirm presses one, Back goes back
+fn menu_navigation(
+    mut commands: Commands,
+    actions: Actions,
+    pause_state: Option<Res<State<PauseState>>>,
+    mut stick_held: Local<bool>,
+    mut button_q: Query<(Entity, &MenuButton, &GlobalTransform, &mut Interaction, Has<Focused>)>,
+) {
+    // UI coordinates, y points down the screen
+    let mut direction = None;
+    for (action, dir) in [
+        (Action::MenuUp, Vec2::NEG_Y),
+        (Action::MenuDown, Vec2::Y),
+        (Action::MenuLeft, Vec2::NEG_X),
+        (Action::MenuRight, Vec2::X),
+    ] {
+        if actions.just_pressed(action) {
+            direction = Some(dir);
+        }
+    }
+
+    // The stick moves one button per push rather than one per frame
+    let stick = actions.left_stick();
+    match (stick.length() > 0.5).then_some(stick) {
+        Some(stick) if !*stick_held => {
+            *stick_held = true;
+            direction = Some(match stick.x.abs() > stick.y.abs() {
+                true => Vec2::new(stick.x.signum(), 0.0),
+                false => Vec2::new(0.0, -stick.y.signum()),
+            });
+        }
+        Some(_) => {}
+        None => *stick_held = false,
+    }
+
+    let activate = actions.just_pressed(Action::Confirm);
+    // In game `toggle_pause` already steps back out of the pause screens, so a key
+    // bound to both Pause and Back (Escape by default) mustn't go back a second time
+    let back = actions.just_pressed(Action::Back) && !(pause_state.is_some() && actions.just_pressed(Action::Pause));
+
+    let focused = button_q
+        .iter()
+        .find(|(.., is_focused)| *is_focused)
+        .map(|(entity, _, transform, ..)| (entity, transform.translation().truncate()));
+
+    if direction.is_some() || (activate && focused.is_none()) {
+        let target = match (focused, direction) {
+            // Nearest button in that direction, preferring ones in line with the current one
+            (Some((current, from)), Some(dir)) => button_q
+                .iter()
+                .filter(|(entity, ..)| *entity != current)
+                .filter_map(|(entity, _, transform, ..)| {
+                    let offset = transform.translation().truncate() - from;
+                    let along = offset.dot(dir);
+                    (along > 1.0).then_some((entity, along + 2.0 * offset.perp_dot(dir).abs()))
+                })
+                .min_by(|a, b| a.1.total_cmp(&b.1))
+                .map(|(entity, _)| entity),
+            (Some(_), None) => None,
+            // Nothing focused yet, start at the top left button
+            (None, _) => button_q
+                .iter()
+                .map(|(entity, _, transform, ..)| (entity, transform.translation()))
+                .min_by(|a, b| a.1.y.total_cmp(&b.1.y).then(a.1.x.total_cmp(&b.1.x)))
+                .map(|(entity, _)| entity),
+        };
+        if let Some(target) = target {
+            set_focus(&mut commands, focused.map(|(entity, _)| entity), target);
+        }
+        return;
+    }
+
+    let pressed = if activate {
+        focused.map(|(entity, _)| entity)
+    } else if back {
+        // Resume before Back whatever order the query returns them in, and never
+        // the way out to the main menu, which would throw the run away
+        let find = |wanted: fn(&MenuButton) -> bool| {
+            button_q.iter().find(|(_, button, ..)| wanted(button)).map(|(entity, ..)| entity)
+        };
+        find(|button| matches!(button, MenuButton::Resume)).or_else(|| find(|button| matches!(button, MenuButton::Back)))
+    } else {
+        None
+    };
+
+    if let Some(entity) = pressed {
+        if let Ok((.., mut interaction, _)) = button_q.get_mut(entity) {
+            *interaction = Interaction::Pressed;
+            commands.entity(entity).insert(NavPressed);
+        }
+    }
+}
+
+// Keeps the mouse and keyboard from showing two different buttons as selected
+type HoverChanged = (Changed<Interaction>, With<MenuButton>);
+
+fn focus_on_hover(
+    mut commands: Commands,
+    hovered_q: Query<(Entity, &Interaction), HoverChanged>,
+    focused_q: Query<Entity, With<Focused>>,
+) {
+    for (entity, interaction) in hovered_q.iter() {
+        if *interaction == Interaction::Hovered && !focused_q.contains(entity) {
+            set_focus(&mut commands, focused_q.get_single().ok(), entity);
+        }
+    }
+}
+
+fn set_focus(commands: &mut Commands, old: Option<Entity>, new: Entity) {
+    if let Some(old) = old {
+        commands.entity(old).remove::<(Focused, Outline)>();
+    }
+    commands
+        .entity(new)
+        .insert((Focused, Outline::new(Val::Px(3.0), Val::ZERO, Color::WHITE)));
+}
+
+// Generates a fresh maze into assets/map.txt, which the next game loads
+fn generate_maze(settings: &GenSettings, seed: u64, origin: &mut MazeOrigin) {
+    let mat = settings.build(seed);
+    origin.0 = Some((settings.algorithm, seed));
+    match code::to_code(&mat) {
+        Ok(code) => println!("Maze code: {}", code),
+        Err(e) => println!("{}", e),
+    }
+
+    // Previews for level browsers and printed handouts
+    let options = export::ExportOptions::default();
+    #[cfg(feature = "image")]
+    if let Err(e) = export::save_png(&mat, "assets/map.png", &options) {
+        println!("{}", e);
+    }
+    if let Err(e) = export::save_svg(&mat, "assets/map.svg", &options) {
+        println!("{}", e);
+    }
+
+    let res = map::save(&mat, "assets/map.txt");
+    match res {
+        Ok(()) => {},
+        Err(e) => {println!("{}", e)},
+    }
+}
+
+// m:ss.s
+pub fn format_time(seconds: f32) -> String {
+    format!("{}:{:04.1}", (seconds / 60.0) as u32, seconds % 60.0)
+}
+
+// The root node of whichever menu screen is open
+type AnyMenu = Or<(
+    With<MainMenu>,
+    With<GenerateMenu>,
+    With<WinMenu>,
+    With<PauseMenu>,
+    With<SettingsMenu>,
+    With<ControlsMenu>,
+)>;
+
 fn cleanup_menu(
     mut commands: Commands,
-    menu_query: Query<Entity, Or<(With<MainMenu>, With<GenerateMenu>)>>,
+    menu_query: Query<Entity, AnyMenu>,
 ) {
     for entity in menu_query.iter() {
         commands.entity(entity).despawn_recursive();
     }
 }
 
-
-fn writefunc(vector: Vec<Vec<char>>, filename: &str) -> Result<()> {
-    let mut file = File::create(filename)?;
-    
-    for row in vector {
-        let row_str: Vec<String> = row.iter()
-            .map(|&val| val.to_string())
-            .collect();
-        
-        let line = row_str.join("") + "\n";
-        file.write_all(line.as_bytes())?;
-    }
-    
-    Ok(())
-}
\ No newline at end of file
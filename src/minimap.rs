@@ -0,0 +1,291 @@
+use bevy::{
+    prelude::*,
+    render::{
+        render_asset::RenderAssetUsages,
+        render_resource::{Extent3d, TextureDimension, TextureFormat, TextureUsages},
+        texture::ImageSampler,
+        view::RenderLayers,
+    },
+};
+use crate::{
+    camera::GameCamera,
+    export::{self, ExportOptions},
+    input::{Action, Actions},
+    menu::{GameState, PauseState},
+    player::Player,
+    world::{Explored, Exploring, LevelEntity, Maze, MazeLoaded},
+};
+
+// Render layer holding the flat map, seen only by the map camera
+pub const MAP_LAYER: usize = 1;
+
+// Side of the square texture the map camera renders into, in pixels
+const MAP_TEXTURE_SIZE: u32 = 512;
+
+// Side of the minimap in the corner of the screen
+const MINIMAP_SIZE: f32 = 220.0;
+
+// Tiles the player hasn't seen yet, the same as the map camera's background
+const FOG: [u8; 4] = [13, 13, 13, 255];
+
+pub struct MinimapPlugin;
+
+impl Plugin for MinimapPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MapView>()
+            .add_systems(Startup, setup_map_texture)
+            .add_systems(OnEnter(GameState::InGame), spawn_map.after(MazeLoaded))
+            .add_systems(Update, (
+                cycle_map_view.run_if(in_state(PauseState::Running)),
+                (update_marker, update_map_node, reveal_map.after(Exploring)).run_if(in_state(GameState::InGame)),
+            ).chain());
+    }
+}
+
+// How the map is shown, cycled with the map action
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum MapView {
+    #[default]
+    Corner,
+    Full,
+    Hidden,
+}
+
+impl MapView {
+    fn next(self) -> MapView {
+        match self {
+            MapView::Corner => MapView::Full,
+            MapView::Full => MapView::Hidden,
+            MapView::Hidden => MapView::Corner,
+        }
+    }
+}
+
+// The image the map camera renders into and the UI shows
+#[derive(Resource)]
+pub struct MapTexture(pub Handle<Image>);
+
+// The maze image on the map quad, with the fully revealed pixels to copy from
+#[derive(Resource)]
+struct MapTiles {
+    image: Handle<Image>,
+    pixels: Vec<u8>,
+    width: usize,
+}
+
+// Arrow on the map showing where the player is and which way they are looking
+#[derive(Component)]
+struct MapMarker;
+
+#[derive(Component)]
+struct MapNode;
+
+fn setup_map_texture(mut commands: Commands, mut images: ResMut<Assets<Image>>) {
+    let size = Extent3d {
+        width: MAP_TEXTURE_SIZE,
+        height: MAP_TEXTURE_SIZE,
+        ..default()
+    };
+    let mut image = Image::new_fill(
+        size,
+        TextureDimension::D2,
+        &[0, 0, 0, 0],
+        TextureFormat::Bgra8UnormSrgb,
+        RenderAssetUsages::default(),
+    );
+    image.texture_descriptor.usage =
+        TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST | TextureUsages::RENDER_ATTACHMENT;
+
+    commands.insert_resource(MapTexture(images.add(image)));
+}
+
+// Lays the whole maze out as one flat textured quad under the map camera, so the map
+// doesn't depend on which wall chunks happen to be streamed in
+fn spawn_map(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut images: ResMut<Assets<Image>>,
+    map_texture: Res<MapTexture>,
+    view: Res<MapView>,
+    maze: Res<Maze>,
+) {
+    let m = maze.0.len() as f32;
+    let n = maze.0.iter().map(|line| line.len()).max().unwrap_or(0) as f32;
+    let options = ExportOptions {
+        cell_size: 1,
+        ..default()
+    };
+    let (w, h, pixels) = export::to_rgba(&maze.0, &options);
+    if w == 0 || h == 0 {
+        return;
+    }
+
+    let mut tiles = Image::new(
+        Extent3d {
+            width: w,
+            height: h,
+            ..default()
+        },
+        TextureDimension::D2,
+        pixels.clone(),
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::default(),
+    );
+    tiles.sampler = ImageSampler::nearest();
+    let tiles = images.add(tiles);
+    commands.insert_resource(MapTiles {
+        image: tiles.clone(),
+        pixels,
+        width: w as usize,
+    });
+
+    commands.spawn((
+        PbrBundle {
+            mesh: meshes.add(Plane3d::default().mesh().size(n, m)),
+            material: materials.add(StandardMaterial {
+                base_color_texture: Some(tiles),
+                unlit: true,
+                ..default()
+            }),
+            transform: Transform::from_xyz(n/2.0 - 0.5, 0.0, m/2.0 - 0.5),
+            ..default()
+        },
+        RenderLayers::layer(MAP_LAYER),
+        Name::new("Map"),
+        LevelEntity,
+    ));
+
+    // Keep the arrow readable on big mazes, where one tile is only a few pixels
+    let scale = (n.max(m) / 30.0).max(1.0);
+    commands.spawn((
+        PbrBundle {
+            mesh: meshes.add(Triangle3d::new(
+                Vec3::new(0.0, 0.0, -0.6),
+                Vec3::new(-0.4, 0.0, 0.4),
+                Vec3::new(0.4, 0.0, 0.4),
+            )),
+            material: materials.add(StandardMaterial {
+                base_color: Color::srgb(1.0, 0.5, 0.0),
+                unlit: true,
+                cull_mode: None,
+                ..default()
+            }),
+            transform: Transform::from_xyz(0.0, 0.1, 0.0).with_scale(Vec3::splat(scale)),
+            ..default()
+        },
+        RenderLayers::layer(MAP_LAYER),
+        MapMarker,
+        Name::new("Map Marker"),
+        LevelEntity,
+    ));
+
+    commands.spawn((
+        ImageBundle {
+            style: map_node_style(*view),
+            image: UiImage::new(map_texture.0.clone()),
+            background_color: Color::srgba(1.0, 1.0, 1.0, 0.9).into(),
+            ..default()
+        },
+        MapNode,
+        Name::new("Minimap"),
+        LevelEntity,
+    ));
+}
+
+// Only the parts of the maze the player has seen show up on the map. The whole map is
+// fogged once for a new level, after that only newly seen tiles are drawn in
+fn reveal_map(
+    explored: Res<Explored>,
+    tiles: Option<Res<MapTiles>>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    let Some(tiles) = tiles else {
+        return;
+    };
+    let fog_all = tiles.is_added();
+    if !fog_all && (!explored.is_changed() || explored.revealed().is_empty()) {
+        return;
+    }
+    let Some(image) = images.get_mut(&tiles.image) else {
+        return;
+    };
+
+    if fog_all {
+        for (i, (pixel, revealed)) in image.data.chunks_exact_mut(4).zip(tiles.pixels.chunks_exact(4)).enumerate() {
+            let seen = explored.is_seen(i / tiles.width, i % tiles.width);
+            pixel.copy_from_slice(if seen { revealed } else { &FOG });
+        }
+        return;
+    }
+
+    for &(r, c) in explored.revealed() {
+        let i = (r * tiles.width + c) * 4;
+        if let (Some(pixel), Some(revealed)) = (image.data.get_mut(i..i + 4), tiles.pixels.get(i..i + 4)) {
+            pixel.copy_from_slice(revealed);
+        }
+    }
+}
+
+fn cycle_map_view(actions: Actions, mut view: ResMut<MapView>) {
+    if actions.just_pressed(Action::ToggleMap) {
+        *view = view.next();
+    }
+}
+
+type Marker = (With<MapMarker>, Without<Player>, Without<GameCamera>);
+
+fn update_marker(
+    player_q: Query<&Transform, With<Player>>,
+    cam_q: Query<&Transform, (With<GameCamera>, Without<Player>)>,
+    mut marker_q: Query<&mut Transform, Marker>,
+) {
+    let (Ok(player), Ok(cam)) = (player_q.get_single(), cam_q.get_single()) else {
+        return;
+    };
+    let heading = Vec3::new(cam.forward().x, 0.0, cam.forward().z);
+
+    for mut marker in marker_q.iter_mut() {
+        marker.translation.x = player.translation.x;
+        marker.translation.z = player.translation.z;
+        if heading.length_squared() > 0.0 {
+            marker.look_to(heading, Vec3::Y);
+        }
+    }
+}
+
+fn update_map_node(view: Res<MapView>, mut node_q: Query<&mut Style, With<MapNode>>) {
+    if !view.is_changed() {
+        return;
+    }
+    for mut style in node_q.iter_mut() {
+        *style = map_node_style(*view);
+    }
+}
+
+// A small square in the corner, or most of the screen for the overview
+fn map_node_style(view: MapView) -> Style {
+    match view {
+        MapView::Corner => Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(10.0),
+            right: Val::Px(10.0),
+            width: Val::Px(MINIMAP_SIZE),
+            height: Val::Px(MINIMAP_SIZE),
+            ..default()
+        },
+        MapView::Full => Style {
+            position_type: PositionType::Absolute,
+            top: Val::Vh(10.0),
+            left: Val::Vw(50.0),
+            margin: UiRect::left(Val::Vh(-40.0)),
+            width: Val::Vh(80.0),
+            height: Val::Vh(80.0),
+            ..default()
+        },
+        MapView::Hidden => Style {
+            display: Display::None,
+            ..default()
+        },
+    }
+}
@@ -1,59 +1,126 @@
-use std::{fs::File, io::{BufRead, BufReader, Error}};
 use bevy::prelude::*;
 use bevy_third_person_camera::*;
 use bevy_rapier3d::prelude::*;
-use crate::menu::GameState;
+use crate::{
+    algo,
+    camera::GameCamera,
+    input::{Action, Actions, STICK_DEADZONE},
+    menu::{GameState, PauseState},
+    settings::GameSettings,
+    world::{LevelEntity, Maze, MazeLoaded},
+};
+
+const SPRINT_MULTIPLIER: f32 = 1.8;
 
 pub struct PlayerPlugin;
 
 impl Plugin for PlayerPlugin {
     fn build(&self, app: &mut App) {
         app
-            .add_systems(OnEnter(GameState::InGame), spawn_player)
-            .add_systems(Update, (player_movement).run_if(in_state(GameState::InGame)));
+            .init_resource::<RunStats>()
+            .add_systems(OnEnter(GameState::InGame), (spawn_player, reset_run_stats).after(MazeLoaded))
+            .add_systems(Update, (
+                (player_movement, toggle_flashlight).run_if(in_state(PauseState::Running)),
+                track_run_stats.run_if(in_state(GameState::InGame)),
+            ));
 
     }
 }
 
 #[derive(Component)]
-struct Player;
+pub struct Player;
+
+#[derive(Component)]
+pub struct Flashlight;
 
 
 #[derive(Component)]
 struct Speed(f32);
 
+// Time and distance of the current attempt at the maze
+#[derive(Resource, Default)]
+pub struct RunStats {
+    pub elapsed: f32,
+    // Tiles actually travelled, after collisions
+    pub distance: f32,
+    // Steps along the shortest S -> E route
+    pub optimal: Option<usize>,
+}
+
+impl RunStats {
+    // Share of the distance walked that a perfect run would also have needed
+    pub fn efficiency(&self, progress: usize) -> f32 {
+        if self.distance <= 0.0 {
+            return 1.0;
+        }
+        (progress as f32 / self.distance).clamp(0.0, 1.0)
+    }
+}
+
+fn reset_run_stats(mut stats: ResMut<RunStats>, maze: Res<Maze>) {
+    *stats = RunStats {
+        optimal: algo::solve(&maze.0).map(|path| path.len() - 1),
+        ..default()
+    };
+}
+
+fn track_run_stats(
+    time: Res<Time>,
+    mut stats: ResMut<RunStats>,
+    output_q: Query<&KinematicCharacterControllerOutput, With<Player>>,
+) {
+    stats.elapsed += time.delta_seconds();
+    if let Ok(output) = output_q.get_single() {
+        stats.distance += output.effective_translation.xz().length();
+    }
+}
+
 fn player_movement(
-    keys: Res<ButtonInput<KeyCode>>,
+    actions: Actions,
     time: Res<Time>,
     mut player_q : Query<(&mut Transform, &Speed, &mut KinematicCharacterController), With<Player>>,
-    cam_q : Query<&Transform , (With<Camera3d>, Without<Player>)>,
+    cam_q : Query<&Transform , (With<GameCamera>, Without<Player>)>,
 ) {
     if let Ok((mut player_transform, player_speed, mut controller)) = player_q.get_single_mut() {
-        let cam = match cam_q.get_single() {
-            Ok(c) => c,
-            Err(e) => Err(format!("Error retrieving camera: {}", e)).unwrap(),
+        let Ok(cam) = cam_q.get_single() else {
+            return;
         };
 
+        // Only the camera's heading matters, so looking up or down in first person doesn't slow the player
+        let flat = |v: Vec3| Vec3::new(v.x, 0.0, v.z).normalize_or_zero();
+        let (forward, right) = (flat(*cam.forward()), flat(*cam.right()));
         let mut direction = Vec3::ZERO;
 
-        if keys.pressed(KeyCode::KeyW) {
-            direction += *cam.forward();
+        if actions.pressed(Action::MoveForward) {
+            direction += forward;
         }
         
-        if keys.pressed(KeyCode::KeyS) {
-            direction += *cam.back();
+        if actions.pressed(Action::MoveBack) {
+            direction -= forward;
+        }
+
+        if actions.pressed(Action::MoveLeft) {
+            direction -= right;
+        }
+
+        if actions.pressed(Action::MoveRight) {
+            direction += right;
         }
 
-        if keys.pressed(KeyCode::KeyA) {
-            direction += *cam.left();
+        // Full speed on the keyboard, proportional to how far the stick is pushed on a gamepad
+        let mut throttle = 1.0;
+        let stick = actions.left_stick();
+        if direction == Vec3::ZERO && stick.length() > STICK_DEADZONE {
+            direction = right * stick.x + forward * stick.y;
+            throttle = ((stick.length() - STICK_DEADZONE) / (1.0 - STICK_DEADZONE)).min(1.0);
         }
 
-        if keys.pressed(KeyCode::KeyD) {
-            direction += *cam.right();
+        if actions.pressed(Action::Sprint) {
+            throttle *= SPRINT_MULTIPLIER;
         }
 
         direction.y = 0.0;
-        let movement = direction.normalize_or_zero() * player_speed.0 * time.delta_seconds();
+        let movement = direction.normalize_or_zero() * player_speed.0 * throttle * time.delta_seconds();
         
         controller.translation = Some(movement);
 
@@ -67,40 +134,61 @@ fn player_movement(
 }
 
 
+// Interact switches the flashlight on and off
+fn toggle_flashlight(
+    actions: Actions,
+    mut flashlight_q: Query<&mut Visibility, With<Flashlight>>,
+) {
+    if !actions.just_pressed(Action::Interact) {
+        return;
+    }
+
+    for mut visibility in flashlight_q.iter_mut() {
+        *visibility = match *visibility {
+            Visibility::Hidden => Visibility::Inherited,
+            _ => Visibility::Hidden,
+        };
+    }
+}
 
 fn spawn_player(
     mut commands: Commands, 
     assets : Res<AssetServer>,
+    maze: Res<Maze>,
+    settings: Res<GameSettings>,
 ) {
     let flashlight = (
         SpotLightBundle{
             spot_light : SpotLight {
             color : Color::linear_rgba(1.0, 1.0, 0.47, 1.0),
             range:100.0,
-            intensity: 1000000.0,
+            intensity: settings.flashlight_intensity,
             outer_angle: 0.8,
             inner_angle: 0.4,
-            shadows_enabled:true,
+            shadows_enabled: settings.shadows,
             ..Default::default()
             },
             transform: Transform::from_xyz(0.0,1.0, -0.5),
             ..Default::default()
         }, 
-        Name::new("Flashlight"));
+        Name::new("Flashlight"),
+        Flashlight);
 
-    let start_pos = get_start_point().unwrap();
+    let start_pos = algo::find_tile(&maze.0, 'S').unwrap_or((0, 0));
 
     let player = (
     SceneBundle {
         scene : assets.load("Player.gltf#Scene0"),
-        transform : Transform::from_xyz(start_pos[1] as f32 , 0.5, start_pos[0] as f32),
+        transform : Transform::from_xyz(start_pos.1 as f32 , 0.5, start_pos.0 as f32),
         ..default()
     },
     Speed(5.0),
     Player,
     ThirdPersonCameraTarget,
     Name::new("Player"),
-    Collider::cuboid(0.5, 1.0, 0.5),
+    LevelEntity,
+    // Narrower than a tile so the player fits down one tile wide corridors
+    Collider::cuboid(0.3, 1.0, 0.3),
     RigidBody::KinematicPositionBased
 );
 
@@ -114,23 +202,3 @@ fn spawn_player(
 
 }
 
-
-
-fn get_start_point() -> Result<[usize; 2], Error> {
-    let mut vec : [usize; 2] = [0, 0];
-    let file = File::open("assets/map.txt")?;
-    let reader = BufReader::new(file);
-
-    for line in reader.lines().enumerate() {
-        let r = line.0;
-        let line = line.1?;
-        for ch in line.chars().enumerate() {
-            if ch.1 == 'S' {
-                vec = [r ,ch.0];
-                break;
-            }
-        }
-    }
-    
-    Ok(vec)
-}
\ No newline at end of file
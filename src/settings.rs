@@ -0,0 +1,164 @@
+use std::{error::Error, fs, path::{Path, PathBuf}};
+use bevy::{
+    audio::GlobalVolume,
+    pbr::PointLightShadowMap,
+    prelude::*,
+    window::{PresentMode, PrimaryWindow, WindowMode},
+};
+use bevy_third_person_camera::ThirdPersonCamera;
+use serde::{Deserialize, Serialize};
+use crate::{camera::CameraMode, player::Flashlight};
+
+pub struct SettingsPlugin;
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .insert_resource(GameSettings::load())
+            .add_systems(Update, apply_settings.run_if(resource_changed::<GameSettings>));
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Quality {
+    Low,
+    Medium,
+    #[default]
+    High,
+}
+
+impl Quality {
+    pub fn next(self) -> Quality {
+        match self {
+            Quality::Low => Quality::Medium,
+            Quality::Medium => Quality::High,
+            Quality::High => Quality::Low,
+        }
+    }
+}
+
+// Player preferences, saved to settings.toml in the user's config directory
+#[derive(Resource, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GameSettings {
+    pub quality: Quality,
+    pub shadows: bool,
+    pub flashlight_intensity: f32,
+    pub mouse_sensitivity: f32,
+    pub camera: CameraMode,
+    // Leave walls the player hasn't seen yet out of the world
+    pub hide_unexplored: bool,
+    // Closest and farthest the third person camera may zoom
+    pub zoom_min: f32,
+    pub zoom_max: f32,
+    // 0.0 - 1.0
+    pub volume: f32,
+    pub fullscreen: bool,
+    pub vsync: bool,
+}
+
+impl Default for GameSettings {
+    fn default() -> Self {
+        GameSettings {
+            quality: Quality::default(),
+            shadows: true,
+            flashlight_intensity: 1000000.0,
+            mouse_sensitivity: 1.0,
+            camera: CameraMode::default(),
+            hide_unexplored: false,
+            zoom_min: 1.0,
+            zoom_max: 3.0,
+            volume: 1.0,
+            fullscreen: false,
+            vsync: true,
+        }
+    }
+}
+
+// A file in the labyrinth folder of the user's config directory
+pub fn config_file(name: &str) -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("labyrinth").join(name))
+}
+
+impl GameSettings {
+    pub fn path() -> Option<PathBuf> {
+        config_file("settings.toml")
+    }
+
+    // Falls back to the defaults when there is no settings file yet or it can't be read
+    pub fn load() -> GameSettings {
+        let Some(path) = Self::path() else {
+            return GameSettings::default();
+        };
+        if !path.exists() {
+            return GameSettings::default();
+        }
+
+        match Self::read(&path) {
+            Ok(settings) => settings,
+            Err(e) => {
+                println!("Could not read {}: {}", path.display(), e);
+                GameSettings::default()
+            }
+        }
+    }
+
+    fn read(path: &Path) -> Result<GameSettings, Box<dyn Error>> {
+        Ok(toml::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    pub fn save(&self) -> Result<(), Box<dyn Error>> {
+        let path = Self::path().ok_or("no config directory")?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, toml::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn apply_settings(
+    settings: Res<GameSettings>,
+    mut msaa: ResMut<Msaa>,
+    mut shadow_map: ResMut<PointLightShadowMap>,
+    mut global_volume: ResMut<GlobalVolume>,
+    mut window_q: Query<&mut Window, With<PrimaryWindow>>,
+    mut cam_q: Query<&mut ThirdPersonCamera>,
+    mut point_light_q: Query<&mut PointLight>,
+    mut spot_light_q: Query<(&mut SpotLight, Has<Flashlight>)>,
+) {
+    (*msaa, shadow_map.size) = match settings.quality {
+        Quality::Low => (Msaa::Off, 512),
+        Quality::Medium => (Msaa::Sample2, 1024),
+        Quality::High => (Msaa::Sample4, 2048),
+    };
+    *global_volume = GlobalVolume::new(settings.volume);
+
+    if let Ok(mut window) = window_q.get_single_mut() {
+        window.mode = match settings.fullscreen {
+            true => WindowMode::BorderlessFullscreen,
+            false => WindowMode::Windowed,
+        };
+        window.present_mode = match settings.vsync {
+            true => PresentMode::AutoVsync,
+            false => PresentMode::AutoNoVsync,
+        };
+    }
+
+    for mut cam in cam_q.iter_mut() {
+        cam.sensitivity = Vec2::splat(settings.mouse_sensitivity);
+        cam.zoom.min = settings.zoom_min;
+        cam.zoom.max = settings.zoom_max;
+    }
+
+    for mut light in point_light_q.iter_mut() {
+        light.shadows_enabled = settings.shadows;
+    }
+    for (mut light, is_flashlight) in spot_light_q.iter_mut() {
+        light.shadows_enabled = settings.shadows;
+        if is_flashlight {
+            light.intensity = settings.flashlight_intensity;
+        }
+    }
+}
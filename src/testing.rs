@@ -0,0 +1,41 @@
+//! Fixtures shared by the unit tests.
+
+use std::ops::Range;
+use crate::algo::Algorithm;
+
+/// Reads a maze written as one line of text per row, like `map.txt`.
+pub fn grid(text: &str) -> Vec<Vec<char>> {
+    text.lines().map(|line| line.chars().collect()).collect()
+}
+
+/// A maze built by one of the generators, with what was asked of it.
+pub struct Generated {
+    pub algorithm: Algorithm,
+    pub row: usize,
+    pub col: usize,
+    pub seed: u64,
+    pub maz: Vec<Vec<char>>,
+}
+
+impl Generated {
+    /// Names the maze in assertion messages.
+    pub fn label(&self) -> String {
+        format!("{} {}x{} seed {}", self.algorithm, self.row, self.col, self.seed)
+    }
+}
+
+/// Every generator at every size with every seed.
+pub fn generated(sizes: &[(usize, usize)], seeds: Range<u64>) -> impl Iterator<Item = Generated> + '_ {
+    Algorithm::ALL.into_iter().flat_map(move |algorithm| {
+        let seeds = seeds.clone();
+        sizes.iter().flat_map(move |&(row, col)| {
+            seeds.clone().map(move |seed| Generated {
+                algorithm,
+                row,
+                col,
+                seed,
+                maz: algorithm.generate(row, col, seed),
+            })
+        })
+    })
+}
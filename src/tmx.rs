@@ -0,0 +1,318 @@
+use std::{fmt, fs, io, path::Path};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use roxmltree::{Document, Node};
+
+// Gids carry the flip flags in their top bits
+const FLIP_FLAGS: u32 = 0xF000_0000;
+
+// Largest map side accepted, well past anything the game streams comfortably
+const MAX_SIZE: usize = 2048;
+
+#[derive(Debug)]
+pub enum TmxError {
+    Io(io::Error),
+    Xml(roxmltree::Error),
+    Unsupported(String),
+    Invalid(String),
+}
+
+impl fmt::Display for TmxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TmxError::Io(e) => write!(f, "{}", e),
+            TmxError::Xml(e) => write!(f, "invalid tmx file: {}", e),
+            TmxError::Unsupported(what) => write!(f, "unsupported tmx feature: {}", what),
+            TmxError::Invalid(what) => write!(f, "invalid tmx file: {}", what),
+        }
+    }
+}
+
+impl std::error::Error for TmxError {}
+
+impl From<io::Error> for TmxError {
+    fn from(e: io::Error) -> Self {
+        TmxError::Io(e)
+    }
+}
+
+impl From<roxmltree::Error> for TmxError {
+    fn from(e: roxmltree::Error) -> Self {
+        TmxError::Xml(e)
+    }
+}
+
+/// Loads a map authored in the Tiled editor into the `map.txt` tile grid.
+///
+/// Tile layers named `walls` (or with a `walls` bool property) turn every
+/// non-empty tile into a wall; without one, the first tile layer is used.
+/// Objects whose type, class or name is `start` or `end` become `S` and `E`.
+/// Any other object marks an entity spawn point, written as its `tile`
+/// string property or the lowercased first letter of its type. Spawn points
+/// are lowercase letters, anything else becomes `*`.
+pub fn from_tmx<P: AsRef<Path>>(path: P) -> Result<Vec<Vec<char>>, TmxError> {
+    let text = fs::read_to_string(path)?;
+    from_str(&text)
+}
+
+pub fn from_str(text: &str) -> Result<Vec<Vec<char>>, TmxError> {
+    let doc = Document::parse(text)?;
+    let map = doc.root_element();
+    if !map.has_tag_name("map") {
+        return Err(TmxError::Invalid("root element is not <map>".into()));
+    }
+    if map.attribute("infinite") == Some("1") {
+        return Err(TmxError::Unsupported("infinite maps".into()));
+    }
+
+    let col = number(map, "width")? as usize;
+    let row = number(map, "height")? as usize;
+    let tile_w = number(map, "tilewidth")? as f32;
+    let tile_h = number(map, "tileheight")? as f32;
+    if row > MAX_SIZE || col > MAX_SIZE {
+        return Err(TmxError::Unsupported(format!("maps larger than {}x{} tiles", MAX_SIZE, MAX_SIZE)));
+    }
+
+    let layers: Vec<Node> = map.descendants().filter(|n| n.has_tag_name("layer")).collect();
+    let walls: Vec<&Node> = layers.iter().filter(|l| is_wall_layer(l)).collect();
+    let walls = if walls.is_empty() { layers.iter().take(1).collect() } else { walls };
+
+    let mut maz = vec![vec!['.'; col]; row];
+    for layer in walls {
+        let gids = layer_gids(layer)?;
+        if gids.len() != row * col {
+            return Err(TmxError::Invalid("layer size does not match the map".into()));
+        }
+        for (i, gid) in gids.into_iter().enumerate() {
+            if gid & !FLIP_FLAGS != 0 {
+                maz[i / col][i % col] = '#';
+            }
+        }
+    }
+
+    let objects = map
+        .descendants()
+        .filter(|n| n.has_tag_name("objectgroup"))
+        .flat_map(|g| g.children().filter(|n| n.has_tag_name("object")));
+
+    for object in objects {
+        let x = float(object, "x");
+        let y = float(object, "y");
+        let w = float(object, "width");
+        let h = float(object, "height");
+
+        // Tile objects are anchored at their bottom-left corner, everything else at the top-left
+        let cy = if object.attribute("gid").is_some() { y - h / 2.0 } else { y + h / 2.0 };
+        let c = ((x + w / 2.0) / tile_w).floor();
+        let r = (cy / tile_h).floor();
+        let tile = object_tile(object);
+        if c < 0.0 || r < 0.0 || c as usize >= col || r as usize >= row {
+            // A level can do without a spawn point, but not without its start or exit
+            if tile == 'S' || tile == 'E' {
+                return Err(TmxError::Invalid(format!("`{}` object lies outside the map", tile)));
+            }
+            continue;
+        }
+
+        maz[r as usize][c as usize] = tile;
+    }
+
+    Ok(maz)
+}
+
+fn is_wall_layer(layer: &Node) -> bool {
+    layer.attribute("name").is_some_and(|n| n.eq_ignore_ascii_case("walls"))
+        || property(*layer, "walls") == Some("true")
+}
+
+fn object_tile(object: Node) -> char {
+    if let Some(tile) = property(object, "tile").and_then(|t| t.chars().next()).and_then(placeable) {
+        return tile;
+    }
+
+    // Newer Tiled versions write the class and leave an empty `type` behind
+    let attribute = |name| object.attribute(name).filter(|t: &&str| !t.is_empty());
+    let kind = attribute("type")
+        .or(attribute("class"))
+        .or(attribute("name"))
+        .unwrap_or("");
+
+    if kind.eq_ignore_ascii_case("start") {
+        'S'
+    } else if kind.eq_ignore_ascii_case("end") {
+        'E'
+    } else {
+        kind.chars().next().map(|c| c.to_ascii_lowercase()).and_then(placeable).unwrap_or('*')
+    }
+}
+
+// Only the characters the game places something for: the markers and spawn points
+fn placeable(tile: char) -> Option<char> {
+    match tile {
+        'S' | 'E' | '*' => Some(tile),
+        c if c.is_ascii_alphabetic() => Some(c.to_ascii_lowercase()),
+        _ => None,
+    }
+}
+
+fn layer_gids(layer: &Node) -> Result<Vec<u32>, TmxError> {
+    let data = layer
+        .children()
+        .find(|n| n.has_tag_name("data"))
+        .ok_or_else(|| TmxError::Invalid("tile layer without <data>".into()))?;
+
+    if data.children().any(|n| n.has_tag_name("chunk")) {
+        return Err(TmxError::Unsupported("chunked layer data".into()));
+    }
+
+    let text = data.text().unwrap_or("").trim();
+    match (data.attribute("encoding"), data.attribute("compression")) {
+        (Some("csv"), None) => text
+            .split(',')
+            .map(|v| v.trim().parse().map_err(|_| TmxError::Invalid(format!("bad gid {:?}", v))))
+            .collect(),
+        (Some("base64"), None) => {
+            let bytes = STANDARD
+                .decode(text)
+                .map_err(|_| TmxError::Invalid("layer data is not valid base64".into()))?;
+            Ok(bytes
+                .chunks_exact(4)
+                .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+                .collect())
+        }
+        (None, None) => Ok(data
+            .children()
+            .filter(|n| n.has_tag_name("tile"))
+            .map(|t| t.attribute("gid").and_then(|g| g.parse().ok()).unwrap_or(0))
+            .collect()),
+        (_, Some(compression)) => Err(TmxError::Unsupported(format!(
+            "{} compressed layers, save the map with CSV or uncompressed base64",
+            compression
+        ))),
+        (Some(encoding), None) => Err(TmxError::Unsupported(format!("{} encoding", encoding))),
+    }
+}
+
+fn property<'a>(node: Node<'a, '_>, name: &str) -> Option<&'a str> {
+    node.children()
+        .filter(|n| n.has_tag_name("properties"))
+        .flat_map(|p| p.children())
+        .find(|p| p.has_tag_name("property") && p.attribute("name") == Some(name))
+        .and_then(|p| p.attribute("value"))
+}
+
+fn number(node: Node, name: &str) -> Result<u32, TmxError> {
+    node.attribute(name)
+        .and_then(|v| v.parse().ok())
+        .ok_or_else(|| TmxError::Invalid(format!("missing or bad `{}` attribute", name)))
+}
+
+fn float(node: Node, name: &str) -> f32 {
+    node.attribute(name).and_then(|v| v.parse().ok()).unwrap_or(0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::grid;
+
+    fn map(layers: &str, objects: &str) -> String {
+        format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<map version="1.10" orientation="orthogonal" width="4" height="3" tilewidth="16" tileheight="16" infinite="0">
+ {}
+ <objectgroup id="3" name="markers">{}</objectgroup>
+</map>"#,
+            layers, objects
+        )
+    }
+
+    const CSV_WALLS: &str = r#"<layer id="1" name="Walls" width="4" height="3">
+  <data encoding="csv">1,1,1,1,
+0,0,0,2147483649,
+1,1,1,1</data>
+ </layer>"#;
+
+    #[test]
+    fn reads_csv_walls_and_markers() {
+        let text = map(CSV_WALLS, r#"
+  <object id="1" name="start" x="0" y="16" width="16" height="16"/>
+  <object id="2" type="end" x="32" y="16" width="16" height="16"/>"#);
+        assert_eq!(from_str(&text).unwrap(), grid("####\nS.E#\n####"));
+    }
+
+    #[test]
+    fn reads_base64_and_xml_layers() {
+        let gids: Vec<u8> = [1u32, 0, 0, 1, 0, 0, 0, 0, 1, 1, 1, 1]
+            .iter()
+            .flat_map(|g| g.to_le_bytes())
+            .collect();
+        let base64 = format!(
+            r#"<layer id="1" name="Ground" width="4" height="3"><data encoding="base64">{}</data></layer>"#,
+            STANDARD.encode(gids)
+        );
+        assert_eq!(from_str(&map(&base64, "")).unwrap(), grid("#..#\n....\n####"));
+
+        // No layer named walls, so the one flagged by property is used
+        let xml = r#"<layer id="1" name="Floor" width="4" height="3"><data><tile gid="1"/></data></layer>
+ <layer id="2" name="Blocks" width="4" height="3">
+  <properties><property name="walls" type="bool" value="true"/></properties>
+  <data>
+   <tile/><tile/><tile/><tile gid="3"/>
+   <tile/><tile/><tile/><tile/>
+   <tile gid="3"/><tile/><tile/><tile/>
+  </data>
+ </layer>"#;
+        assert_eq!(from_str(&map(xml, "")).unwrap(), grid("...#\n....\n#..."));
+    }
+
+    #[test]
+    fn falls_back_to_class_when_type_is_empty() {
+        let text = map(CSV_WALLS, r#"
+  <object id="1" name="door" type="" class="start" x="0" y="16" width="16" height="16"/>
+  <object id="2" type="" class="end" x="32" y="16" width="16" height="16"/>
+  <object id="3" type="" name="Key" x="16" y="16" width="16" height="16"/>"#);
+        assert_eq!(from_str(&text).unwrap(), grid("####\nSkE#\n####"));
+    }
+
+    #[test]
+    fn places_tile_objects_and_tile_properties() {
+        // Tile objects sit on their bottom-left corner. Tiles the game has no use
+        // for fall back to the object's type, then to a plain spawn point
+        let text = map(CSV_WALLS, r#"
+  <object id="1" gid="5" x="16" y="32" width="16" height="16">
+   <properties><property name="tile" value="K"/></properties>
+  </object>
+  <object id="2" type="Chest" x="0" y="16" width="16" height="16">
+   <properties><property name="tile" value="@"/></properties>
+  </object>
+  <object id="3" name="42" x="32" y="16" width="16" height="16"/>
+  <object id="4" name="lamp" x="100" y="100" width="16" height="16"/>"#);
+        assert_eq!(from_str(&text).unwrap(), grid("####\nck*#\n####"));
+    }
+
+    #[test]
+    fn rejects_markers_outside_the_map() {
+        for class in ["start", "end"] {
+            let object = format!(r#"<object id="1" class="{}" x="100" y="16" width="16" height="16"/>"#, class);
+            assert!(matches!(from_str(&map(CSV_WALLS, &object)), Err(TmxError::Invalid(_))));
+        }
+    }
+
+    #[test]
+    fn rejects_unsupported_maps() {
+        let infinite = map(CSV_WALLS, "").replace(r#"infinite="0""#, r#"infinite="1""#);
+        assert!(matches!(from_str(&infinite), Err(TmxError::Unsupported(_))));
+
+        let zlib = map(r#"<layer id="1" name="Walls"><data encoding="base64" compression="zlib">eJw=</data></layer>"#, "");
+        assert!(matches!(from_str(&zlib), Err(TmxError::Unsupported(_))));
+
+        let short = map(r#"<layer id="1" name="Walls"><data encoding="csv">1,1,1</data></layer>"#, "");
+        assert!(matches!(from_str(&short), Err(TmxError::Invalid(_))));
+
+        let huge = map(CSV_WALLS, "").replace(r#"width="4" height="3" tilewidth"#, r#"width="100000" height="100000" tilewidth"#);
+        assert!(matches!(from_str(&huge), Err(TmxError::Unsupported(_))));
+
+        assert!(matches!(from_str("<tileset/>"), Err(TmxError::Invalid(_))));
+        assert!(matches!(from_str("<map"), Err(TmxError::Xml(_))));
+    }
+}
@@ -1,21 +1,127 @@
-use std::{fs::File, io::{self, BufRead}, path::Path};
-use bevy::{ math::Affine2, prelude::*, render::{mesh::Indices, render_asset:: RenderAssetUsages}};
+use std::collections::HashMap;
+use bevy::{ math::Affine2, prelude::*, render::{mesh::{Indices, PrimitiveTopology}, render_asset:: RenderAssetUsages, texture::{ImageAddressMode, ImageLoaderSettings, ImageSampler, ImageSamplerDescriptor}}};
 use bevy_rapier3d::prelude::*;
-use crate::menu::GameState;
+use crate::{
+    algo::{self, Algorithm},
+    flythrough::AfterFlythrough,
+    map,
+    menu::{GameState, PauseState},
+    player::Player,
+    settings::GameSettings,
+};
+
+// Walls are batched into square chunks of this many tiles, one mesh per chunk
+const CHUNK_SIZE: usize = 16;
+
+// Chunks within this many chunks of the player's are kept spawned
+const VIEW_DISTANCE: usize = 3;
+
+// Floor tiles covered by one copy of the grass texture
+const FLOOR_TEXTURE_TILES: f32 = 4.0;
+
+const BOUNDS_HEIGHT: f32 = 3.0;
+const BOUNDS_THICKNESS: f32 = 1.0;
+
+// How many tiles away the player can make out walls by flashlight
+const SIGHT_RADIUS: usize = 8;
 
 pub struct WorldPlugin;
 
 impl Plugin for WorldPlugin {
     fn build(&self,app: &mut App) {
-        app.add_systems(OnEnter(GameState::InGame), (spawn_floor, spawn_light, spawn_walls));
+        app.register_type::<SpawnPoint>()
+            .init_resource::<Maze>()
+            .init_resource::<MazeOrigin>()
+            .init_resource::<LoadedChunks>()
+            .init_resource::<Explored>()
+            .add_systems(OnEnter(GameState::InGame), (load_maze, reset_explored).chain().in_set(MazeLoaded))
+            .add_systems(OnEnter(GameState::InGame), (spawn_floor, spawn_light, spawn_walls).after(MazeLoaded))
+            .add_systems(Update, (
+                (explore.in_set(Exploring), stream_chunks, reveal_walls).chain(),
+                check_exit,
+            ).run_if(in_state(GameState::InGame)))
+            .add_systems(OnExit(GameState::InGame), despawn_level);
     }
 }
 
-fn spawn_light(mut commands: Commands) {
+// Wall chunks are streamed around this entity instead of the player while it exists
+#[derive(Component)]
+pub struct StreamFocus;
+
+// Sensor on the `E` tile that wins the game when the player steps on it
+#[derive(Component)]
+pub struct Exit;
+
+// Everything spawned for the current level; despawned when leaving the game
+#[derive(Component)]
+pub struct LevelEntity;
+
+// Systems that read `Maze` when entering the game run after this set
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MazeLoaded;
+
+// Systems that read the tiles `Explored` just revealed run after this set, so none are missed
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Exploring;
+
+// The tile grid of the level being played
+#[derive(Resource, Default)]
+pub struct Maze(pub Vec<Vec<char>>);
+
+// Algorithm and seed behind assets/map.txt, if it was generated this session
+#[derive(Resource, Default)]
+pub struct MazeOrigin(pub Option<(Algorithm, u64)>);
+
+// Tiles the player has had in sight during the current run
+#[derive(Resource, Default)]
+pub struct Explored {
+    seen: Vec<Vec<bool>>,
+    // Tiles first seen in the latest update
+    revealed: Vec<(usize, usize)>,
+    // The player's tile at the latest update
+    from: Option<(usize, usize)>,
+}
+
+impl Explored {
+    pub fn is_seen(&self, r: usize, c: usize) -> bool {
+        self.seen.get(r).and_then(|line| line.get(c)).copied().unwrap_or(false)
+    }
+
+    // Tiles first seen in the latest update
+    pub fn revealed(&self) -> &[(usize, usize)] {
+        &self.revealed
+    }
+
+    // Share of the maze's floor tiles seen so far, 0.0 - 1.0
+    pub fn fraction(&self, maz: &[Vec<char>]) -> f32 {
+        let floor = || maz.iter().enumerate().flat_map(|(r, line)| {
+            line.iter().enumerate().filter(|&(_, &t)| t != '#').map(move |(c, _)| (r, c))
+        });
+        let total = floor().count();
+        if total == 0 {
+            return 0.0;
+        }
+        floor().filter(|&(r, c)| self.is_seen(r, c)).count() as f32 / total as f32
+    }
+}
+
+#[derive(Resource)]
+struct WallMaterial(Handle<StandardMaterial>);
+
+// Wall chunks currently spawned, keyed by (chunk row, chunk col). Chunks
+// without any walls are remembered as `None` so they are not rebuilt
+#[derive(Resource, Default)]
+struct LoadedChunks(HashMap<(usize, usize), Option<Entity>>);
+
+// Entity spawn point from an imported level, tagged with its map.txt character
+#[derive(Component, Reflect)]
+pub struct SpawnPoint(pub char);
+
+fn spawn_light(mut commands: Commands, settings: Res<GameSettings>) {
     let light =( 
         PointLightBundle {
             point_light : PointLight {
-                shadows_enabled: true,
+                shadows_enabled: settings.shadows,
                 intensity: 100.0,
                 ..Default::default()
             },
@@ -23,6 +129,7 @@ fn spawn_light(mut commands: Commands) {
             ..Default::default()
         },
         Name::new("Main Light"),
+        LevelEntity,
     );
 
     commands.spawn(light);
@@ -32,42 +139,67 @@ fn spawn_floor(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
-    server : Res<AssetServer>
+    server : Res<AssetServer>,
+    maze: Res<Maze>,
 ) {
-    let floor_material_handle: Handle<Image> = server.load("grass.jpg");
+    // Repeat the grass texture instead of stretching one copy over the whole maze
+    let floor_material_handle: Handle<Image> = server.load_with_settings("grass.jpg", |settings: &mut ImageLoaderSettings| {
+        settings.sampler = ImageSampler::Descriptor(ImageSamplerDescriptor {
+            address_mode_u: ImageAddressMode::Repeat,
+            address_mode_v: ImageAddressMode::Repeat,
+            ..default()
+        });
+    });
 
-    let mut lines = read_lines("assets/map.txt").unwrap();
-
-    let n = match lines.next() {
-        Some(Ok(line)) => line.chars().count(),
-        Some(Err(_e)) => return,
-        None => 0,
-    };
-    
-    let m = 1 + lines.count();
-
-    println!("{}, {}", m, n);
+    let m = maze.0.len() as f32;
+    let n = maze.0.iter().map(|line| line.len()).max().unwrap_or(0) as f32;
 
     let floor = (
         PbrBundle {
-            mesh: meshes.add(Plane3d::default().mesh().size(n as f32, m as f32)),
-            material: materials.add(floor_material_handle),
-            transform: Transform::from_xyz((n as f32)/2.0 - 0.5, 0.0, (m as f32)/2.0 - 0.5),
+            mesh: meshes.add(Plane3d::default().mesh().size(n, m)),
+            material: materials.add(StandardMaterial {
+                base_color_texture: Some(floor_material_handle),
+                uv_transform: Affine2::from_scale(Vec2::new(n, m) / FLOOR_TEXTURE_TILES),
+                ..default()
+            }),
+            transform: Transform::from_xyz(n/2.0 - 0.5, 0.0, m/2.0 - 0.5),
             ..Default::default()
         },
         Name::new("Floor"),
+        LevelEntity,
         RigidBody::Fixed,
-        Collider::cuboid(50.0, 0.0, 50.0)
+        Collider::cuboid(n/2.0, 0.0, m/2.0)
     );
 
     commands.spawn(floor);
+
+    // Invisible walls just outside the maze, so openings on the outer edge
+    // don't let the player walk off the floor
+    let center = Vec3::new(n/2.0 - 0.5, BOUNDS_HEIGHT/2.0, m/2.0 - 0.5);
+    let half = BOUNDS_THICKNESS/2.0;
+    let bounds = (
+        TransformBundle::from_transform(Transform::from_translation(center)),
+        Name::new("Maze Bounds"),
+        LevelEntity,
+        RigidBody::Fixed,
+        Collider::compound(vec![
+            (Vec3::new(0.0, 0.0, -(m/2.0 + half)), Quat::IDENTITY, Collider::cuboid(n/2.0 + BOUNDS_THICKNESS, BOUNDS_HEIGHT/2.0, half)),
+            (Vec3::new(0.0, 0.0, m/2.0 + half), Quat::IDENTITY, Collider::cuboid(n/2.0 + BOUNDS_THICKNESS, BOUNDS_HEIGHT/2.0, half)),
+            (Vec3::new(-(n/2.0 + half), 0.0, 0.0), Quat::IDENTITY, Collider::cuboid(half, BOUNDS_HEIGHT/2.0, m/2.0 + BOUNDS_THICKNESS)),
+            (Vec3::new(n/2.0 + half, 0.0, 0.0), Quat::IDENTITY, Collider::cuboid(half, BOUNDS_HEIGHT/2.0, m/2.0 + BOUNDS_THICKNESS)),
+        ]),
+    );
+
+    commands.spawn(bounds);
 }
 
 fn spawn_walls (
     mut commands : Commands,
     mut meshes : ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
-    server : Res<AssetServer> 
+    server : Res<AssetServer>,
+    maze: Res<Maze>,
+    mut loaded: ResMut<LoadedChunks>,
 ) {
 let mat: [f32; 4]  =  [1.0, 0.0, 0.0, 2.0];
     let wall_handle: Handle<Image> = server.load("textures\\patched-brickwork-bl\\patched-brickwork_albedo.png");
@@ -87,155 +219,381 @@ let mat: [f32; 4]  =  [1.0, 0.0, 0.0, 2.0];
     let blue_material = materials.add(Color::srgb(0.0, 0.0, 1.0));
     let green_material = materials.add(Color::srgb(0.0, 1.0, 0.0));
 
-    let mesh1 = Mesh::new(bevy::render::mesh::PrimitiveTopology::TriangleList, RenderAssetUsages::MAIN_WORLD | RenderAssetUsages::RENDER_WORLD)
-                .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, 
-                    vec![
-                        // top (facing towards +y)
-                        [-0.5, 1.0, -0.5], // vertex with index 0
-                        [0.5, 1.0, -0.5], // vertex with index 1
-                        [0.5, 1.0, 0.5], // etc. until 23
-                        [-0.5, 1.0, 0.5],
-                        // bottom   (-y)
-                        [-0.5, -0.5, -0.5],
-                        [0.5, -0.5, -0.5],
-                        [0.5, -0.5, 0.5],
-                        [-0.5, -0.5, 0.5],
-                        // right    (+x)
-                        [0.5, -0.5, -0.5],
-                        [0.5, -0.5, 0.5],
-                        [0.5, 1.0, 0.5], // This vertex is at the same position as vertex with index 2, but they'll have different UV and normal
-                        [0.5, 1.0, -0.5],
-                        // left     (-x)
-                        [-0.5, -0.5, -0.5],
-                        [-0.5, -0.5, 0.5],
-                        [-0.5, 1.0, 0.5],
-                        [-0.5, 1.0, -0.5],
-                        // back     (+z)
-                        [-0.5, -0.5, 0.5],
-                        [-0.5, 1.0, 0.5],
-                        [0.5, 1.0, 0.5],
-                        [0.5, -0.5, 0.5],
-                        // forward  (-z)
-                        [-0.5, -0.5, -0.5],
-                        [-0.5, 1.0, -0.5],
-                        [0.5, 1.0, -0.5],
-                        [0.5, -0.5, -0.5],
-                    ],
-                ).with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, 
-                    vec![
-                        // Assigning the UV coords for the top side.
-                        [0.0, 0.2], [0.0, 0.0], [1.0, 0.0], [1.0, 0.2],
-                        // Assigning the UV coords for the bottom side.
-                        [0.0, 0.45], [0.0, 0.25], [1.0, 0.25], [1.0, 0.45],
-                        // Assigning the UV coords for the right side.
-                        [1.0, 0.45], [0.0, 0.45], [0.0, 0.2], [1.0, 0.2],
-                        // Assigning the UV coords for the left side.
-                        [1.0, 0.45], [0.0, 0.45], [0.0, 0.2], [1.0, 0.2],
-                        // Assigning the UV coords for the back side.
-                        [0.0, 0.45], [0.0, 0.2], [1.0, 0.2], [1.0, 0.45],
-                        // Assigning the UV coords for the forward side.
-                        [0.0, 0.45], [0.0, 0.2], [1.0, 0.2], [1.0, 0.45],
-                    ],
-                )
-                .with_inserted_attribute(
-                    Mesh::ATTRIBUTE_NORMAL,
-                    vec![
-                        // Normals for the top side (towards +y)
-                        [0.0, 1.0, 0.0],
-                        [0.0, 1.0, 0.0],
-                        [0.0, 1.0, 0.0],
-                        [0.0, 1.0, 0.0],
-                        // Normals for the bottom side (towards -y)
-                        [0.0, -1.0, 0.0],
-                        [0.0, -1.0, 0.0],
-                        [0.0, -1.0, 0.0],
-                        [0.0, -1.0, 0.0],
-                        // Normals for the right side (towards +x)
-                        [1.0, 0.0, 0.0],
-                        [1.0, 0.0, 0.0],
-                        [1.0, 0.0, 0.0],
-                        [1.0, 0.0, 0.0],
-                        // Normals for the left side (towards -x)
-                        [-1.0, 0.0, 0.0],
-                        [-1.0, 0.0, 0.0],
-                        [-1.0, 0.0, 0.0],
-                        [-1.0, 0.0, 0.0],
-                        // Normals for the back side (towards +z)
-                        [0.0, 0.0, 1.0],
-                        [0.0, 0.0, 1.0],
-                        [0.0, 0.0, 1.0],
-                        [0.0, 0.0, 1.0],
-                        // Normals for the forward side (towards -z)
-                        [0.0, 0.0, -1.0],
-                        [0.0, 0.0, -1.0],
-                        [0.0, 0.0, -1.0],
-                        [0.0, 0.0, -1.0],
-                    ],
-                )
-                .with_inserted_indices(Indices::U32(vec![
-                    0,3,1 , 1,3,2, // triangles making up the top (+y) facing side.
-                    4,5,7 , 5,6,7, // bottom (-y)
-                    8,11,9 , 9,11,10, // right (+x)
-                    12,13,15 , 13,14,15, // left (-x)
-                    16,19,17 , 17,19,18, // back (+z)
-                    20,21,23 , 21,22,23, // forward (-z)
-                ]));
-
-
-    for (_c, row) in read_lines("assets/map.txt").into_iter().enumerate() {
-        for (y, line) in row.enumerate().into_iter() {
-            for (x, tile) in line.unwrap().chars().enumerate().into_iter(){
-                let pos = Vec3::new(x as f32, 0.5, y as f32);
-                // println!("Tile[{}][{}] : {}", x, y, tile);
-                match tile {
-                    '#' => {
-                        commands.spawn((
-                            PbrBundle {
-                                mesh : meshes.add(mesh1.clone()),
-                                material: wall_material.clone(),
-                                transform: Transform::from_translation(pos),
-                                ..default()
-                            },
-                            RigidBody::Fixed,
-                            Collider::cuboid(0.25,1.0,0.25)
-                        ))
-                    },
-                    'S' => {
-                        commands.spawn(
-                            (
-                            PbrBundle {
-                                mesh : meshes.add(Cuboid::new(1.0, 0.1, 1.0)),
-                                transform: Transform::from_translation(Vec3::new(x as f32, 0.0, y as f32)),
-                                material : blue_material.clone(),
-                                ..default()
-                            },
-                            Name::new("Starting Point"),
-                        ))
-                    },
-                    'E' => {
-                        commands.spawn(
-                            (
-                            PbrBundle {
-                                mesh : meshes.add(Cuboid::new(1.0, 0.1, 1.0)),
-                                transform: Transform::from_translation(Vec3::new(x as f32, 0.0, y as f32)),
-                                material : green_material.clone(),
-                                ..default()
-                            },
-                            Name::new("Ending Point"),
-                        ))
+    let marker_mesh = meshes.add(Cuboid::new(1.0, 0.1, 1.0));
+
+    // Wall chunks themselves are spawned around the player by `stream_chunks`
+    commands.insert_resource(WallMaterial(wall_material));
+    loaded.0.clear();
+
+    for (y, line) in maze.0.iter().enumerate() {
+        for (x, &tile) in line.iter().enumerate() {
+            match tile {
+                'S' => {
+                    commands.spawn(
+                        (
+                        PbrBundle {
+                            mesh : marker_mesh.clone(),
+                            transform: Transform::from_translation(Vec3::new(x as f32, 0.0, y as f32)),
+                            material : blue_material.clone(),
+                            ..default()
+                        },
+                        Name::new("Starting Point"),
+                        LevelEntity,
+                    ));
+                },
+                'E' => {
+                    commands.spawn(
+                        (
+                        PbrBundle {
+                            mesh : marker_mesh.clone(),
+                            transform: Transform::from_translation(Vec3::new(x as f32, 0.0, y as f32)),
+                            material : green_material.clone(),
+                            ..default()
+                        },
+                        Name::new("Ending Point"),
+                        LevelEntity,
+                        Exit,
+                        Collider::cuboid(0.3, 1.0, 0.3),
+                        Sensor,
+                        ActiveEvents::COLLISION_EVENTS,
+                        // The player is kinematic, which fixed colliders ignore by default
+                        ActiveCollisionTypes::default() | ActiveCollisionTypes::KINEMATIC_STATIC,
+                    ));
+                },
+                c if c.is_ascii_lowercase() || c == '*' => {
+                    commands.spawn((
+                        SpatialBundle::from_transform(Transform::from_translation(Vec3::new(x as f32, 0.0, y as f32))),
+                        SpawnPoint(c),
+                        Name::new("Spawn Point"),
+                        LevelEntity,
+                    ));
+                },
+                _ => {},
+            }
+        }
+    }
+}
+
+fn load_maze(mut maze: ResMut<Maze>) {
+    maze.0 = match map::load("assets/map.txt") {
+        Ok(maz) => maz,
+        Err(e) => {
+            println!("{}", e);
+            Vec::new()
+        }
+    };
+}
+
+fn reset_explored(mut explored: ResMut<Explored>, maze: Res<Maze>) {
+    *explored = Explored {
+        seen: maze.0.iter().map(|line| vec![false; line.len()]).collect(),
+        ..default()
+    };
+}
+
+// Looks around again whenever the player steps onto another tile
+fn explore(
+    maze: Res<Maze>,
+    player_q: Query<&Transform, With<Player>>,
+    mut explored: ResMut<Explored>,
+) {
+    let Ok(player) = player_q.get_single() else {
+        return;
+    };
+    let tile = (player.translation.z.round().max(0.0) as usize, player.translation.x.round().max(0.0) as usize);
+    if explored.from == Some(tile) {
+        return;
+    }
+
+    let explored = explored.as_mut();
+    explored.from = Some(tile);
+    explored.revealed.clear();
+    for (r, c) in algo::visible_from(&maze.0, tile, SIGHT_RADIUS) {
+        if let Some(seen) = explored.seen.get_mut(r).and_then(|line| line.get_mut(c)) {
+            if !*seen {
+                *seen = true;
+                explored.revealed.push((r, c));
+            }
+        }
+    }
+}
+
+// Reaching the exit shows the solution before the win screen
+fn check_exit(
+    mut commands: Commands,
+    mut collisions: EventReader<CollisionEvent>,
+    exit_q: Query<(), With<Exit>>,
+    player_q: Query<(), With<Player>>,
+    mut next_state: ResMut<NextState<PauseState>>,
+) {
+    for event in collisions.read() {
+        if let CollisionEvent::Started(a, b, _) = *event {
+            if (exit_q.contains(a) && player_q.contains(b)) || (exit_q.contains(b) && player_q.contains(a)) {
+                commands.insert_resource(AfterFlythrough::Won);
+                next_state.set(PauseState::Flythrough);
+            }
+        }
+    }
+}
+
+fn despawn_level(
+    mut commands: Commands,
+    mut loaded: ResMut<LoadedChunks>,
+    query: Query<Entity, With<LevelEntity>>,
+) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    loaded.0.clear();
+    commands.remove_resource::<WallMaterial>();
+}
+
+#[allow(clippy::too_many_arguments)]
+fn stream_chunks(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut loaded: ResMut<LoadedChunks>,
+    maze: Res<Maze>,
+    wall_material: Option<Res<WallMaterial>>,
+    settings: Res<GameSettings>,
+    explored: Res<Explored>,
+    focus_q: Query<&Transform, With<StreamFocus>>,
+    player_q: Query<&Transform, With<Player>>,
+) {
+    let center = focus_q.get_single().or_else(|_| player_q.get_single());
+    let (Some(wall_material), Ok(center)) = (wall_material, center) else {
+        return;
+    };
+
+    let chunk_rows = maze.0.len().div_ceil(CHUNK_SIZE);
+    let chunk_cols = maze.0.iter().map(|line| line.len()).max().unwrap_or(0).div_ceil(CHUNK_SIZE);
+    let pr = center.translation.z.round().max(0.0) as usize / CHUNK_SIZE;
+    let pc = center.translation.x.round().max(0.0) as usize / CHUNK_SIZE;
+
+    // Unload one chunk further out than we load, so walking back and forth
+    // over a chunk border doesn't rebuild the same chunks every frame
+    loaded.0.retain(|&(r, c), entity| {
+        let keep = r.abs_diff(pr) <= VIEW_DISTANCE + 1 && c.abs_diff(pc) <= VIEW_DISTANCE + 1;
+        if let (false, Some(entity)) = (keep, entity) {
+            commands.entity(*entity).despawn_recursive();
+        }
+        keep
+    });
+
+    for r in pr.saturating_sub(VIEW_DISTANCE)..(pr + VIEW_DISTANCE + 1).min(chunk_rows) {
+        for c in pc.saturating_sub(VIEW_DISTANCE)..(pc + VIEW_DISTANCE + 1).min(chunk_cols) {
+            if loaded.0.contains_key(&(r, c)) {
+                continue;
+            }
+
+            let (chunk_r, chunk_c) = (r * CHUNK_SIZE, c * CHUNK_SIZE);
+            // The collider covers every wall even while some of them are hidden
+            let shown = |r, c| !settings.hide_unexplored || explored.is_seen(r, c);
+            let entity = chunk_has_walls(&maze.0, chunk_r, chunk_c).then(|| {
+                let mesh = wall_chunk_mesh(&maze.0, chunk_r, chunk_c, shown);
+                commands.spawn((
+                    PbrBundle {
+                        mesh : mesh.map_or_else(Handle::default, |mesh| meshes.add(mesh)),
+                        material: wall_material.0.clone(),
+                        transform: Transform::from_xyz(chunk_c as f32, 0.5, chunk_r as f32),
+                        ..default()
                     },
-                    _ => commands.spawn(PbrBundle::default()),
-                };
+                    Name::new("Wall Chunk"),
+                    LevelEntity,
+                    RigidBody::Fixed,
+                    wall_chunk_collider(&maze.0, chunk_r, chunk_c),
+                )).id()
+            });
+            loaded.0.insert((r, c), entity);
+        }
+    }
+}
+
+// With unexplored walls hidden, rebuilds the loaded chunks where walls just came into view,
+// or every loaded chunk when the setting changes
+fn reveal_walls(
+    mut meshes: ResMut<Assets<Mesh>>,
+    maze: Res<Maze>,
+    settings: Res<GameSettings>,
+    explored: Res<Explored>,
+    loaded: Res<LoadedChunks>,
+    mut chunk_q: Query<&mut Handle<Mesh>>,
+) {
+    let mut chunks: Vec<(usize, usize)> = if settings.is_changed() {
+        loaded.0.keys().copied().collect()
+    } else if settings.hide_unexplored && explored.is_changed() {
+        explored.revealed.iter().map(|&(r, c)| (r / CHUNK_SIZE, c / CHUNK_SIZE)).collect()
+    } else {
+        return;
+    };
+    chunks.sort_unstable();
+    chunks.dedup();
+
+    let shown = |r, c| !settings.hide_unexplored || explored.is_seen(r, c);
+    for (r, c) in chunks {
+        let Some(&Some(entity)) = loaded.0.get(&(r, c)) else {
+            continue;
+        };
+        if let Ok(mut mesh) = chunk_q.get_mut(entity) {
+            *mesh = wall_chunk_mesh(&maze.0, r * CHUNK_SIZE, c * CHUNK_SIZE, shown)
+                .map_or_else(Handle::default, |chunk| meshes.add(chunk));
+        }
+    }
+}
+
+// One side of the unit wall block; `neighbor` is the (row, col) offset of
+// the tile that covers this side when it is also a wall
+struct WallFace {
+    neighbor: Option<(isize, isize)>,
+    positions: [[f32; 3]; 4],
+    uvs: [[f32; 2]; 4],
+    normal: [f32; 3],
+    indices: [u32; 6],
+}
+
+// The bottom side always rests on the floor, so it is left out
+const WALL_FACES: [WallFace; 5] = [
+    // top (facing towards +y)
+    WallFace {
+        neighbor: None,
+        positions: [[-0.5, 1.0, -0.5], [0.5, 1.0, -0.5], [0.5, 1.0, 0.5], [-0.5, 1.0, 0.5]],
+        uvs: [[0.0, 0.2], [0.0, 0.0], [1.0, 0.0], [1.0, 0.2]],
+        normal: [0.0, 1.0, 0.0],
+        indices: [0, 3, 1, 1, 3, 2],
+    },
+    // right    (+x)
+    WallFace {
+        neighbor: Some((0, 1)),
+        positions: [[0.5, -0.5, -0.5], [0.5, -0.5, 0.5], [0.5, 1.0, 0.5], [0.5, 1.0, -0.5]],
+        uvs: [[1.0, 0.45], [0.0, 0.45], [0.0, 0.2], [1.0, 0.2]],
+        normal: [1.0, 0.0, 0.0],
+        indices: [0, 3, 1, 1, 3, 2],
+    },
+    // left     (-x)
+    WallFace {
+        neighbor: Some((0, -1)),
+        positions: [[-0.5, -0.5, -0.5], [-0.5, -0.5, 0.5], [-0.5, 1.0, 0.5], [-0.5, 1.0, -0.5]],
+        uvs: [[1.0, 0.45], [0.0, 0.45], [0.0, 0.2], [1.0, 0.2]],
+        normal: [-1.0, 0.0, 0.0],
+        indices: [0, 1, 3, 1, 2, 3],
+    },
+    // back     (+z)
+    WallFace {
+        neighbor: Some((1, 0)),
+        positions: [[-0.5, -0.5, 0.5], [-0.5, 1.0, 0.5], [0.5, 1.0, 0.5], [0.5, -0.5, 0.5]],
+        uvs: [[0.0, 0.45], [0.0, 0.2], [1.0, 0.2], [1.0, 0.45]],
+        normal: [0.0, 0.0, 1.0],
+        indices: [0, 3, 1, 1, 3, 2],
+    },
+    // forward  (-z)
+    WallFace {
+        neighbor: Some((-1, 0)),
+        positions: [[-0.5, -0.5, -0.5], [-0.5, 1.0, -0.5], [0.5, 1.0, -0.5], [0.5, -0.5, -0.5]],
+        uvs: [[0.0, 0.45], [0.0, 0.2], [1.0, 0.2], [1.0, 0.45]],
+        normal: [0.0, 0.0, -1.0],
+        indices: [0, 1, 3, 1, 2, 3],
+    },
+];
+
+fn chunk_has_walls(maz: &[Vec<char>], chunk_r: usize, chunk_c: usize) -> bool {
+    maz.iter()
+        .skip(chunk_r)
+        .take(CHUNK_SIZE)
+        .any(|line| line.iter().skip(chunk_c).take(CHUNK_SIZE).any(|&t| t == '#'))
+}
+
+// Combines every wall tile of a chunk that `shown` lets through into a single mesh,
+// positioned relative to the chunk's top-left tile
+fn wall_chunk_mesh(maz: &[Vec<char>], chunk_r: usize, chunk_c: usize, shown: impl Fn(usize, usize) -> bool) -> Option<Mesh> {
+    let is_wall = |r: isize, c: isize| {
+        r >= 0 && c >= 0 &&
+        maz.get(r as usize).and_then(|line| line.get(c as usize)) == Some(&'#')
+    };
+
+    let mut positions = Vec::new();
+    let mut uvs = Vec::new();
+    let mut normals = Vec::new();
+    let mut indices = Vec::new();
+
+    for r in chunk_r..(chunk_r + CHUNK_SIZE).min(maz.len()) {
+        for c in chunk_c..chunk_c + CHUNK_SIZE {
+            if !shown(r, c) {
+                continue;
+            }
+            let (r, c) = (r as isize, c as isize);
+            if !is_wall(r, c) {
+                continue;
+            }
+
+            let dx = (c - chunk_c as isize) as f32;
+            let dz = (r - chunk_r as isize) as f32;
+            for face in &WALL_FACES {
+                // Sides pressed against another wall can never be seen
+                if face.neighbor.is_some_and(|(dr, dc)| is_wall(r + dr, c + dc)) {
+                    continue;
+                }
+
+                let base = positions.len() as u32;
+                positions.extend(face.positions.iter().map(|p| [p[0] + dx, p[1], p[2] + dz]));
+                uvs.extend_from_slice(&face.uvs);
+                normals.extend_from_slice(&[face.normal; 4]);
+                indices.extend(face.indices.iter().map(|i| base + i));
             }
         }
     }
 
+    if positions.is_empty() {
+        return None;
+    }
 
+    Some(
+        Mesh::new(PrimitiveTopology::TriangleList, RenderAssetUsages::MAIN_WORLD | RenderAssetUsages::RENDER_WORLD)
+            .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+            .with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, uvs)
+            .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, normals)
+            .with_inserted_indices(Indices::U32(indices))
+    )
 }
 
+// Greedily covers the chunk's wall tiles with as few rectangles as possible and
+// turns them into one compound collider matching the wall blocks exactly
+fn wall_chunk_collider(maz: &[Vec<char>], chunk_r: usize, chunk_c: usize) -> Collider {
+    let is_wall = |r: usize, c: usize| {
+        maz.get(chunk_r + r).and_then(|line| line.get(chunk_c + c)) == Some(&'#')
+    };
+    let rows = CHUNK_SIZE.min(maz.len().saturating_sub(chunk_r));
+
+    let mut covered = [[false; CHUNK_SIZE]; CHUNK_SIZE];
+    let mut shapes = Vec::new();
+
+    for r in 0..rows {
+        for c in 0..CHUNK_SIZE {
+            if covered[r][c] || !is_wall(r, c) {
+                continue;
+            }
+
+            let mut w = 1;
+            while c + w < CHUNK_SIZE && !covered[r][c + w] && is_wall(r, c + w) {
+                w += 1;
+            }
+            let mut h = 1;
+            while r + h < rows && (c..c + w).all(|cc| !covered[r + h][cc] && is_wall(r + h, cc)) {
+                h += 1;
+            }
+            for line in &mut covered[r..r + h] {
+                line[c..c + w].fill(true);
+            }
+
+            // Wall blocks span half a tile either side of their centre and
+            // rise from the floor to 1.5, with the chunk sitting at y = 0.5
+            let center = Vec3::new(
+                c as f32 + w as f32 / 2.0 - 0.5,
+                0.25,
+                r as f32 + h as f32 / 2.0 - 0.5,
+            );
+            shapes.push((center, Quat::IDENTITY, Collider::cuboid(w as f32 / 2.0, 0.75, h as f32 / 2.0)));
+        }
+    }
 
-fn read_lines<P>(filename: P) -> io::Result<io::Lines<io::BufReader<File>>>
-where P: AsRef<Path>, {
-    let file = File::open(filename)?;
-    Ok(io::BufReader::new(file).lines())
+    Collider::compound(shapes)
 }